@@ -1,7 +1,9 @@
 use crate::Reason;
-use std::collections::BTreeMap;
-use std::fmt::{Display, Formatter};
-use std::iter::FromIterator;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use core::fmt::{Display, Formatter};
+use core::iter::FromIterator;
 
 #[cfg(feature = "minicbor")]
 mod minicbor;
@@ -30,7 +32,7 @@ macro_rules! many_error {
         }
 
         impl Display for ManyErrorCode {
-            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
                 match self.message() {
                     Some(msg) => f.write_str(msg),
                     None => write!(f, "{}", Into::<i64>::into(*self)),
@@ -119,6 +121,18 @@ many_error! {
             => "Invalid Subresource ID. Subresource IDs are 31 bits.",
      -104: SenderCannotBeAnonymous as sender_cannot_be_anonymous()
             => "Invalid Identity; the sender cannot be anonymous.",
+     -105: IdentityMismatch as identity_mismatch(expected, actual)
+            => "Identity mismatch. Expected {expected}, was {actual}.",
+     -106: AnonymousCannotBeDestination as anonymous_cannot_be_destination()
+            => "Invalid Identity; the destination cannot be anonymous.",
+     -107: PublicKeyMismatch as public_key_mismatch(identity)
+            => "The public key does not match the identity {identity}.",
+     -108: InvalidNetworkIdPrefix as invalid_network_id_prefix(actual)
+            => "Network ID string did not start with the right prefix. Expected 'n', was '{actual}'.",
+     -109: InvalidIdentityChecksum as invalid_identity_checksum()
+            => "Identity checksum is invalid.",
+     -110: InvalidMnemonic as invalid_mnemonic(details)
+            => "Invalid mnemonic: {details}.",
 
      // HSM-related errors
      -200: HSMInitError as hsm_init_error(details)
@@ -221,6 +235,13 @@ impl ManyErrorCode {
     pub const fn is_application_specific(&self) -> bool {
         matches!(self, ManyErrorCode::ApplicationSpecific(_))
     }
+
+    /// Whether this code is in the range reserved for identity errors
+    /// (-100 to -199).
+    #[inline]
+    pub fn is_identity_error(&self) -> bool {
+        (-199..=-100).contains(&i64::from(*self))
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -258,6 +279,24 @@ impl ManyError {
         self.code().is_application_specific()
     }
 
+    /// The kind byte that was not recognized, if this is an
+    /// [`ManyErrorCode::InvalidIdentityKind`] error.
+    pub fn invalid_identity_kind_byte(&self) -> Option<u8> {
+        match self.code() {
+            ManyErrorCode::InvalidIdentityKind => self.argument("actual")?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The prefix that was found instead of 'm', if this is an
+    /// [`ManyErrorCode::InvalidIdentityPrefix`] error.
+    pub fn invalid_identity_prefix_str(&self) -> Option<&str> {
+        match self.code() {
+            ManyErrorCode::InvalidIdentityPrefix => self.argument("actual"),
+            _ => None,
+        }
+    }
+
     pub const fn new(
         code: ManyErrorCode,
         message: Option<String>,
@@ -294,7 +333,7 @@ impl ManyError {
 }
 
 impl Display for ManyError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         self.0.fmt(f)
     }
 }
@@ -306,6 +345,7 @@ impl Default for ManyErrorCode {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ManyError {}
 
 impl Default for ManyError {
@@ -380,4 +420,54 @@ mod tests {
 
         assert_eq!(format!("{}", e), "/{}{ZERO}{}}{TWO.");
     }
+
+    #[test]
+    fn keeps_unmatched_brackets() {
+        let mut arguments = BTreeMap::new();
+        arguments.insert("0".to_string(), "ZERO".to_string());
+
+        let e = ManyError::new(
+            ErrorCode::Unknown,
+            Some("}{0 }{{0}{0".to_string()),
+            arguments,
+        );
+
+        assert_eq!(format!("{}", e), "}{0 }{0}{0");
+    }
+
+    #[test]
+    fn identity_errors() {
+        let e = ManyError::invalid_identity();
+        assert_eq!(e.code(), ErrorCode::InvalidIdentity);
+        assert_eq!(i64::from(e.code()), -100);
+        assert!(e.code().is_identity_error());
+        assert_eq!(e.invalid_identity_kind_byte(), None);
+        assert_eq!(e.invalid_identity_prefix_str(), None);
+        assert_eq!(
+            e.to_string(),
+            "Identity is invalid (does not follow the protocol)."
+        );
+
+        let e = ManyError::invalid_identity_kind(7u8);
+        assert_eq!(e.code(), ErrorCode::InvalidIdentityKind);
+        assert_eq!(i64::from(e.code()), -102);
+        assert!(e.code().is_identity_error());
+        assert_eq!(e.invalid_identity_kind_byte(), Some(7));
+        assert_eq!(e.invalid_identity_prefix_str(), None);
+        assert_eq!(e.to_string(), r#"Identity kind "7" was not recognized."#);
+
+        let e = ManyError::invalid_identity_prefix("x");
+        assert_eq!(e.code(), ErrorCode::InvalidIdentityPrefix);
+        assert_eq!(i64::from(e.code()), -101);
+        assert!(e.code().is_identity_error());
+        assert_eq!(e.invalid_identity_kind_byte(), None);
+        assert_eq!(e.invalid_identity_prefix_str(), Some("x"));
+        assert_eq!(
+            e.to_string(),
+            "Identity string did not start with the right prefix. Expected 'm', was 'x'."
+        );
+
+        assert!(!ManyError::unknown("").code().is_identity_error());
+        assert!(!ManyError::hsm_init_error("").code().is_identity_error());
+    }
 }
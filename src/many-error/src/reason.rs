@@ -1,5 +1,7 @@
-use std::collections::BTreeMap;
-use std::fmt::{Display, Formatter};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
 
 #[cfg(feature = "minicbor")]
 pub mod minicbor;
@@ -47,35 +49,40 @@ impl<T> Reason<T> {
 }
 
 impl<T: Display> Display for Reason<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let message = self
             .message
             .clone()
             .unwrap_or_else(|| format!("Error '{}'", self.code));
 
-        let re = regex::Regex::new(r"\{\{|\}\}|\{[^\}\s]*\}").unwrap();
-        let mut current = 0;
+        // Replaces `{field}` with the argument named `field`, and `{{` and
+        // `}}` with a single bracket. Anything else is written as is.
+        let mut rest = message.as_str();
+        while let Some(start) = rest.find(&['{', '}'][..]) {
+            f.write_str(&rest[..start])?;
+            rest = &rest[start..];
 
-        for mat in re.find_iter(&message) {
-            let std::ops::Range { start, end } = mat.range();
-            f.write_str(&message[current..start])?;
-            current = end;
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                f.write_str(&rest[..1])?;
+                rest = &rest[2..];
+                continue;
+            }
 
-            let s = mat.as_str();
-            if s == "{{" {
-                f.write_str("{")?;
-            } else if s == "}}" {
-                f.write_str("}")?;
-            } else {
-                let field = &message[start + 1..end - 1];
-                f.write_str(
-                    self.arguments
-                        .get(field)
-                        .unwrap_or(&"".to_string())
-                        .as_str(),
-                )?;
+            let field = rest.strip_prefix('{').and_then(|s| {
+                let end = s.find(|c: char| c == '}' || c.is_whitespace())?;
+                s[end..].starts_with('}').then(|| &s[..end])
+            });
+            match field {
+                Some(field) => {
+                    f.write_str(self.arguments.get(field).map_or("", String::as_str))?;
+                    rest = &rest[field.len() + 2..];
+                }
+                None => {
+                    f.write_str(&rest[..1])?;
+                    rest = &rest[1..];
+                }
             }
         }
-        f.write_str(&message[current..])
+        f.write_str(rest)
     }
 }
@@ -1,8 +1,11 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use minicbor::data::Type;
 use minicbor::encode::{Error, Write};
 use minicbor::{Decode, Decoder, Encode, Encoder};
 use num_derive::{FromPrimitive, ToPrimitive};
-use std::collections::BTreeMap;
 
 #[derive(FromPrimitive, ToPrimitive)]
 #[repr(i8)]
@@ -2,6 +2,11 @@ use coset::cbor::value::{Integer, Value};
 use coset::iana::{EnumI64, OkpKeyParameter};
 use coset::{CoseKey, Label};
 use many_error::ManyError;
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+use {
+    coset::iana::{Algorithm, Ec2KeyParameter, EllipticCurve, KeyType},
+    std::collections::BTreeSet,
+};
 
 #[cfg(feature = "ed25519")]
 pub mod ed25519;
@@ -9,6 +14,15 @@ pub mod ed25519;
 #[cfg(feature = "ecdsa")]
 pub mod ecdsa;
 
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
+
+/// The PKCS#8 algorithm OID of elliptic curve keys (id-ecPublicKey). The
+/// curve is a parameter of the algorithm.
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+pub const EC_PUBLIC_KEY_OID: pkcs8::ObjectIdentifier =
+    pkcs8::ObjectIdentifier::new("1.2.840.10045.2.1");
+
 /// Assert a COSE key as valid.
 fn check_key(
     cose_key: &CoseKey,
@@ -65,3 +79,114 @@ fn check_key(
 
     Ok(())
 }
+
+/// Overwrites the bytes parameters of a key that was only built to load an
+/// identity, so its private key is not left behind in freed memory.
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+fn zeroize_params(cose_key: &mut CoseKey) {
+    use zeroize::Zeroize;
+
+    for (_, value) in cose_key.params.iter_mut() {
+        if let Value::Bytes(bytes) = value {
+            bytes.zeroize();
+        }
+    }
+}
+
+/// Build an EC2 CoseKey on the given curve.
+///
+/// # Arguments
+///
+/// * `(x, y)` - Public key
+/// * `d` - Private key
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+fn ec2_cose_key(
+    (x, y): (Vec<u8>, Vec<u8>),
+    d: Option<Vec<u8>>,
+    algo: Algorithm,
+    crv: EllipticCurve,
+) -> CoseKey {
+    let mut params: Vec<(Label, Value)> = Vec::from([
+        (
+            Label::Int(Ec2KeyParameter::Crv as i64),
+            Value::from(crv as u64),
+        ),
+        (Label::Int(Ec2KeyParameter::X as i64), Value::Bytes(x)),
+        (Label::Int(Ec2KeyParameter::Y as i64), Value::Bytes(y)),
+    ]);
+    let mut key_ops: BTreeSet<coset::KeyOperation> =
+        BTreeSet::from([coset::KeyOperation::Assigned(
+            coset::iana::KeyOperation::Verify,
+        )]);
+
+    if let Some(d) = d {
+        params.push((Label::Int(Ec2KeyParameter::D as i64), Value::Bytes(d)));
+        key_ops.insert(coset::KeyOperation::Assigned(
+            coset::iana::KeyOperation::Sign,
+        ));
+    }
+
+    // The CoseKeyBuilder is too limited to be used here
+    CoseKey {
+        kty: coset::KeyType::Assigned(KeyType::EC2),
+        alg: Some(coset::Algorithm::Assigned(algo)),
+        key_ops,
+        params,
+        ..Default::default()
+    }
+}
+
+/// Extract the public part of an EC2 key with the given algorithm, or `None`
+/// if the key uses another algorithm.
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+fn ec2_public_key(
+    key: &CoseKey,
+    algo: Algorithm,
+    (crv_name, crv): (&str, EllipticCurve),
+) -> Result<Option<CoseKey>, ManyError> {
+    if key.alg != Some(coset::Algorithm::Assigned(algo)) {
+        return Ok(None);
+    }
+
+    let x = param(key, Ec2KeyParameter::X);
+    let y = param(key, Ec2KeyParameter::Y);
+
+    if let (Some(x), Some(y)) = (x, y) {
+        let x = x
+            .as_bytes()
+            .cloned()
+            .ok_or_else(|| ManyError::unknown(format!("Could not get {crv_name} X parameter")))?;
+        let y = y
+            .as_bytes()
+            .cloned()
+            .ok_or_else(|| ManyError::unknown(format!("Could not get {crv_name} Y parameter")))?;
+        Ok(Some(ec2_cose_key((x, y), None, algo, crv)))
+    } else {
+        Err(ManyError::unknown("Key doesn't have a public key"))
+    }
+}
+
+/// Finds a bytes parameter without copying the others, which may include the
+/// private key.
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+fn param_bytes<'a>(
+    key: &'a CoseKey,
+    label: Ec2KeyParameter,
+    name: &str,
+) -> Result<&'a [u8], ManyError> {
+    Ok(param(key, label)
+        .ok_or_else(|| ManyError::unknown(format!("Could not find the {name} parameter in key")))?
+        .as_bytes()
+        .ok_or_else(|| {
+            ManyError::unknown(format!("Could not convert the {name} parameter to bytes"))
+        })?
+        .as_slice())
+}
+
+#[cfg(any(feature = "ecdsa", feature = "secp256k1"))]
+fn param(key: &CoseKey, label: Ec2KeyParameter) -> Option<&Value> {
+    key.params
+        .iter()
+        .find(|(l, _)| l == &Label::Int(label.to_i64()))
+        .map(|(_, v)| v)
+}
@@ -1,14 +1,14 @@
-use crate::impls::check_key;
-use coset::cbor::value::Value;
-use coset::iana::{Algorithm, Ec2KeyParameter, EllipticCurve, EnumI64, KeyType};
-use coset::{CoseKey, CoseSign1, CoseSign1Builder, Label};
+use crate::impls::{check_key, ec2_cose_key, ec2_public_key, param_bytes, zeroize_params};
+use coset::iana::{Algorithm, Ec2KeyParameter, EllipticCurve, KeyType};
+use coset::{CoseKey, CoseSign1, CoseSign1Builder};
 use many_error::ManyError;
 use many_identity::cose::add_keyset_header;
 use many_identity::{cose, Address, Identity, Verifier};
 use p256::pkcs8::FromPrivateKey;
 use pkcs8::der::Document;
 use signature::{Signature, Signer};
-use std::collections::{BTreeMap, BTreeSet};
+
+pub use crate::impls::EC_PUBLIC_KEY_OID;
 
 /// Build an ECDSA CoseKey
 ///
@@ -17,59 +17,11 @@ use std::collections::{BTreeMap, BTreeSet};
 /// * `(x, y)` - Public key
 /// * `d` - Private key
 pub fn ecdsa_cose_key((x, y): (Vec<u8>, Vec<u8>), d: Option<Vec<u8>>) -> CoseKey {
-    let mut params: Vec<(Label, Value)> = Vec::from([
-        (
-            Label::Int(Ec2KeyParameter::Crv as i64),
-            Value::from(EllipticCurve::P_256 as u64),
-        ),
-        (Label::Int(Ec2KeyParameter::X as i64), Value::Bytes(x)),
-        (Label::Int(Ec2KeyParameter::Y as i64), Value::Bytes(y)),
-    ]);
-    let mut key_ops: BTreeSet<coset::KeyOperation> =
-        BTreeSet::from([coset::KeyOperation::Assigned(
-            coset::iana::KeyOperation::Verify,
-        )]);
-
-    if let Some(d) = d {
-        params.push((Label::Int(Ec2KeyParameter::D as i64), Value::Bytes(d)));
-        key_ops.insert(coset::KeyOperation::Assigned(
-            coset::iana::KeyOperation::Sign,
-        ));
-    }
-
-    // The CoseKeyBuilder is too limited to be used here
-    CoseKey {
-        kty: coset::KeyType::Assigned(KeyType::EC2),
-        alg: Some(coset::Algorithm::Assigned(Algorithm::ES256)),
-        key_ops,
-        params,
-        ..Default::default()
-    }
+    ec2_cose_key((x, y), d, Algorithm::ES256, EllipticCurve::P_256)
 }
 
 pub fn public_key(key: &CoseKey) -> Result<Option<CoseKey>, ManyError> {
-    let params = BTreeMap::from_iter(key.params.clone().into_iter());
-    match key.alg {
-        Some(coset::Algorithm::Assigned(Algorithm::ES256)) => {
-            let x = params.get(&Label::Int(Ec2KeyParameter::X.to_i64()));
-            let y = params.get(&Label::Int(Ec2KeyParameter::Y.to_i64()));
-
-            if let (Some(x), Some(y)) = (x.cloned(), y.cloned()) {
-                let x = x
-                    .as_bytes()
-                    .cloned()
-                    .ok_or_else(|| ManyError::unknown("Could not get ECDSA X parameter"))?;
-                let y = y
-                    .as_bytes()
-                    .cloned()
-                    .ok_or_else(|| ManyError::unknown("Could not get ECDSA Y parameter"))?;
-                Ok(Some(ecdsa_cose_key((x, y), None)))
-            } else {
-                Err(ManyError::unknown("Key doesn't have a public key"))
-            }
-        }
-        _ => Ok(None),
-    }
+    ec2_public_key(key, Algorithm::ES256, ("ECDSA", EllipticCurve::P_256))
 }
 
 #[derive(Clone, Debug)]
@@ -86,13 +38,7 @@ impl EcDsaIdentityInner {
         let public_key = public_key(cose_key)?.ok_or_else(|| ManyError::unknown("Invalid key."))?;
         let address = cose::address_unchecked(&public_key)?;
 
-        let params = BTreeMap::from_iter(cose_key.params.iter().cloned());
-        let d = params
-            .get(&Label::Int(Ec2KeyParameter::D.to_i64()))
-            .ok_or_else(|| ManyError::unknown("Could not find the D parameter in key"))?
-            .as_bytes()
-            .ok_or_else(|| ManyError::unknown("Could not convert the D parameter to bytes"))?
-            .as_slice();
+        let d = param_bytes(cose_key, Ec2KeyParameter::D, "D")?;
 
         let sk = p256::SecretKey::from_bytes(d)
             .map_err(|e| ManyError::unknown(format!("Invalid EcDSA keypair from bytes: {e}")))?;
@@ -153,29 +99,36 @@ impl EcDsaIdentity {
     }
 
     pub fn from_pem<P: AsRef<str>>(pem: P) -> Result<Self, ManyError> {
-        let doc = pkcs8::PrivateKeyDocument::from_pem(pem.as_ref()).unwrap();
+        let doc = pkcs8::PrivateKeyDocument::from_pem(pem.as_ref()).map_err(ManyError::unknown)?;
+        Self::from_der(doc.as_der())
+    }
+
+    /// Load a PKCS#8 DER private key.
+    pub fn from_der(der: &[u8]) -> Result<Self, ManyError> {
+        let doc = pkcs8::PrivateKeyDocument::from_der(der).map_err(ManyError::unknown)?;
         let decoded = doc.decode();
 
-        // EcDSA P256 OID
-        if decoded.algorithm.oid != pkcs8::ObjectIdentifier::new("1.2.840.10045.2.1") {
+        if decoded.algorithm.oid != EC_PUBLIC_KEY_OID {
             return Err(ManyError::unknown(format!(
                 "Invalid OID: {}",
                 decoded.algorithm.oid
             )));
         }
 
-        let sk = p256::SecretKey::from_pkcs8_pem(pem.as_ref()).unwrap();
+        // Only P-256 is supported.
+        let sk = p256::SecretKey::from_pkcs8_der(der).map_err(ManyError::unknown)?;
         let pk = sk.public_key();
         let points: p256::EncodedPoint = pk.into();
 
-        let cose_key = ecdsa_cose_key(
+        let mut cose_key = ecdsa_cose_key(
             (points.x().unwrap().to_vec(), points.y().unwrap().to_vec()),
             Some(sk.to_bytes().to_vec()),
         );
-        Self::from_key(&cose_key)
+        let result = Self::from_key(&cose_key);
+        zeroize_params(&mut cose_key);
+        result
     }
 
-    #[cfg(test)]
     pub(crate) fn try_sign(&self, bytes: &[u8]) -> Result<Vec<u8>, ManyError> {
         self.0.try_sign(bytes)
     }
@@ -209,19 +162,8 @@ impl EcDsaVerifier {
         check_key(cose_key, false, true, KeyType::EC2, Algorithm::ES256, None)?;
         let address = cose::address_unchecked(&public_key)?;
 
-        let params = BTreeMap::from_iter(cose_key.params.clone().into_iter());
-        let x = params
-            .get(&Label::Int(Ec2KeyParameter::X.to_i64()))
-            .ok_or_else(|| ManyError::unknown("Could not find the X parameter in key"))?
-            .as_bytes()
-            .ok_or_else(|| ManyError::unknown("Could not convert the X parameter to bytes"))?
-            .as_slice();
-        let y = params
-            .get(&Label::Int(Ec2KeyParameter::Y.to_i64()))
-            .ok_or_else(|| ManyError::unknown("Could not find the Y parameter in key"))?
-            .as_bytes()
-            .ok_or_else(|| ManyError::unknown("Could not convert the Y parameter to bytes"))?
-            .as_slice();
+        let x = param_bytes(cose_key, Ec2KeyParameter::X, "X")?;
+        let y = param_bytes(cose_key, Ec2KeyParameter::Y, "Y")?;
         let points = p256::EncodedPoint::from_affine_coordinates(x.into(), y.into(), false);
         let pk = p256::ecdsa::VerifyingKey::from_encoded_point(&points)
             .map_err(|e| ManyError::unknown(format!("Could not create a verifying key: {}", e)))?;
@@ -322,7 +264,7 @@ pub mod tests {
                          LDxECgIOQvJJd3UcP1d2rTcBY8XMQDl51gLCvCp9c3v1tz9I/hRCEQcH/d96mNHn\n\
                          SigsOU15Tt1NMHHgrucDBMeDrMZ+uUIDdZbfpvvh0gCtvmvvH5FLs/Y=\n\
                          -----END PRIVATE KEY-----";
-        let _ = EcDsaIdentity::from_pem(pem);
+        EcDsaIdentity::from_pem(pem).unwrap();
     }
 
     #[test]
@@ -356,6 +298,25 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn zeroize_on_drop() {
+        use std::mem::ManuallyDrop;
+        use std::ptr::{addr_of, drop_in_place, read};
+
+        let cose_key = generate_random_ecdsa_cose_key();
+        let mut slot = ManuallyDrop::new(EcDsaIdentityInner::from_key(&cose_key).unwrap());
+        assert!(slot.sk.to_bytes().iter().any(|b| *b != 0));
+
+        // Drop the identity in place; its storage is still owned by `slot`,
+        // and the signing key is only read back as a copy that is not dropped.
+        let inner: *mut EcDsaIdentityInner = &mut *slot;
+        let sk = unsafe {
+            drop_in_place(inner);
+            ManuallyDrop::new(read(addr_of!((*inner).sk)))
+        };
+        assert!(sk.to_bytes().iter().all(|b| *b == 0));
+    }
+
     #[test]
     fn identity_invalid_key_no_sign() {
         let mut cose_key = generate_random_ecdsa_cose_key();
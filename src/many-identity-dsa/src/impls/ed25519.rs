@@ -8,6 +8,10 @@ use many_identity::{cose, Address, Identity, Verifier};
 use pkcs8::der::Document;
 use signature::{Signature, Signer};
 use std::collections::BTreeSet;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The PKCS#8 algorithm OID of Ed25519 keys.
+pub const ED25519_OID: pkcs8::ObjectIdentifier = pkcs8::ObjectIdentifier::new("1.3.101.112");
 
 /// Build an EdDSA CoseKey
 ///
@@ -100,7 +104,8 @@ fn key_pair(cose_key: &CoseKey) -> Result<Keypair, ManyError> {
         .ok_or_else(|| ManyError::unknown("Could not convert the D parameter to bytes"))?
         .as_slice();
 
-    Keypair::from_bytes(&vec![d, x].concat())
+    let bytes = Zeroizing::new([d, x].concat());
+    Keypair::from_bytes(&bytes)
         .map_err(|e| ManyError::unknown(format!("Invalid Ed25519 keypair from bytes: {e}")))
 }
 
@@ -110,12 +115,15 @@ struct Ed25519IdentityInner {
     key_pair: Keypair,
 }
 
+// The key pair zeroes its secret key when dropped, so a clone is another
+// copy that is dropped the same way. The bytes used to copy it are zeroed too.
 impl Clone for Ed25519IdentityInner {
     fn clone(&self) -> Self {
+        let bytes = Zeroizing::new(self.key_pair.to_bytes());
         Ed25519IdentityInner {
             address: self.address,
             public_key: self.public_key.clone(),
-            key_pair: Keypair::from_bytes(&self.key_pair.to_bytes()).unwrap(),
+            key_pair: Keypair::from_bytes(bytes.as_ref()).unwrap(),
         }
     }
 }
@@ -183,11 +191,16 @@ impl Ed25519Identity {
     }
 
     pub fn from_pem<P: AsRef<str>>(pem: P) -> Result<Self, ManyError> {
-        let doc = pkcs8::PrivateKeyDocument::from_pem(pem.as_ref()).unwrap();
+        let doc = pkcs8::PrivateKeyDocument::from_pem(pem.as_ref()).map_err(ManyError::unknown)?;
+        Self::from_der(doc.as_der())
+    }
+
+    /// Load a PKCS#8 DER private key.
+    pub fn from_der(der: &[u8]) -> Result<Self, ManyError> {
+        let doc = pkcs8::PrivateKeyDocument::from_der(der).map_err(ManyError::unknown)?;
         let decoded = doc.decode();
 
-        // Ed25519 OID
-        if decoded.algorithm.oid != pkcs8::ObjectIdentifier::new("1.3.101.112") {
+        if decoded.algorithm.oid != ED25519_OID {
             return Err(ManyError::unknown(format!(
                 "Invalid OID: {}",
                 decoded.algorithm.oid
@@ -195,27 +208,54 @@ impl Ed25519Identity {
         }
 
         // Remove the 0420 header that's in all private keys in pkcs8 for some reason.
-        let sk = ed25519_dalek::SecretKey::from_bytes(&decoded.private_key[2..])
-            .map_err(ManyError::unknown)?;
-        let pk: ed25519_dalek::PublicKey = (&sk).into();
-        let keypair: Keypair = Keypair {
-            secret: sk,
-            public: pk,
-        };
-        let keypair = Keypair::from_bytes(&keypair.to_bytes()).unwrap();
+        let sk =
+            ed25519_dalek::SecretKey::from_bytes(decoded.private_key.get(2..).unwrap_or_default())
+                .map_err(ManyError::unknown)?;
+        Self::from_secret_key(sk)
+    }
 
-        let cose_key = eddsa_cose_key(
-            keypair.public.to_bytes().to_vec(),
-            Some(keypair.secret.to_bytes().to_vec()),
-        );
-        Self::from_key(&cose_key)
+    /// Derive an identity from a 32 bytes seed, which is the Ed25519 secret
+    /// key. The same seed always gives the same identity.
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, ManyError> {
+        let sk = ed25519_dalek::SecretKey::from_bytes(seed).map_err(ManyError::unknown)?;
+        Self::from_secret_key(sk)
+    }
+
+    /// Derive the child key `index` of this key. The derivation is hardened;
+    /// it uses the private key, so the child cannot be derived from the
+    /// public key or address.
+    pub fn derive_child(&self, index: u32) -> Result<Self, ManyError> {
+        use hmac::Mac;
+
+        let mut mac = hmac::Hmac::<sha2::Sha512>::new_from_slice(self.0.key_pair.secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(b"many subresource");
+        mac.update(&index.to_be_bytes());
+        let mut output = mac.finalize().into_bytes();
+        let mut seed = Zeroizing::new([0; 32]);
+        seed.copy_from_slice(&output[..32]);
+        output.as_mut_slice().zeroize();
+        Self::from_seed(&seed)
+    }
+
+    /// Builds the identity from the secret key directly, instead of through a
+    /// `CoseKey` that would hold another copy of the secret key.
+    fn from_secret_key(sk: ed25519_dalek::SecretKey) -> Result<Self, ManyError> {
+        let public: ed25519_dalek::PublicKey = (&sk).into();
+        let public_key = eddsa_cose_key(public.to_bytes().to_vec(), None);
+        let address = cose::address_unchecked(&public_key)?;
+
+        Ok(Self(Ed25519IdentityInner {
+            address,
+            public_key,
+            key_pair: Keypair { secret: sk, public },
+        }))
     }
 
     pub fn public_key(&self) -> CoseKey {
         self.0.public_key.clone()
     }
 
-    #[cfg(test)]
     pub(crate) fn try_sign(&self, bytes: &[u8]) -> Result<Vec<u8>, ManyError> {
         self.0.try_sign(bytes)
     }
@@ -285,6 +325,24 @@ impl Ed25519Verifier {
     }
 }
 
+/// Verify multiple signatures at once, using Ed25519 batch verification.
+/// Items are `(verifier, signature, message)`. Returns true only if all the
+/// signatures are valid; a failed batch does not tell which one is invalid.
+pub(crate) fn verify_batch(items: &[(&Ed25519Verifier, &[u8], &[u8])]) -> bool {
+    let signatures: Result<Vec<_>, _> = items
+        .iter()
+        .map(|(_, signature, _)| ed25519::Signature::from_bytes(signature))
+        .collect();
+    let signatures = match signatures {
+        Ok(signatures) => signatures,
+        Err(_) => return false,
+    };
+    let messages: Vec<&[u8]> = items.iter().map(|(_, _, message)| *message).collect();
+    let public_keys: Vec<_> = items.iter().map(|(v, _, _)| v.public_key).collect();
+
+    ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok()
+}
+
 impl Verifier for Ed25519Verifier {
     fn verify_1(&self, envelope: &CoseSign1) -> Result<Address, ManyError> {
         let address = Address::from_bytes(&envelope.protected.header.key_id)?;
@@ -301,6 +359,21 @@ impl Verifier for Ed25519Verifier {
     }
 }
 
+/// The SLIP-0010 Ed25519 master private key of a (e.g. BIP39) seed.
+#[cfg(feature = "mnemonic")]
+pub(crate) fn slip10_master_key(seed: &[u8]) -> Zeroizing<[u8; 32]> {
+    use hmac::Mac;
+
+    let mut mac = hmac::Hmac::<sha2::Sha512>::new_from_slice(b"ed25519 seed")
+        .expect("HMAC can take a key of any size");
+    mac.update(seed);
+    let mut output = mac.finalize().into_bytes();
+    let mut key = Zeroizing::new([0; 32]);
+    key.copy_from_slice(&output[..32]);
+    output.as_mut_slice().zeroize();
+    key
+}
+
 #[cfg(feature = "testing")]
 pub(crate) fn generate_random_ed25519_cose_key() -> CoseKey {
     use rand_07::rngs::OsRng;
@@ -331,6 +404,36 @@ pub mod tests {
         Ed25519Identity::from_pem(pem).unwrap()
     }
 
+    #[cfg(feature = "mnemonic")]
+    #[test]
+    fn zeroize_on_drop() {
+        use std::mem::ManuallyDrop;
+        use std::ptr::{addr_of, drop_in_place, read};
+
+        let seed = [0x42; 32];
+        let mut slot = ManuallyDrop::new(Ed25519Identity::from_seed(&seed).unwrap().0);
+        assert_eq!(slot.key_pair.secret.as_bytes(), &seed);
+
+        // Drop the identity in place; its storage is still owned by `slot`,
+        // and the secret key is only read back as a copy that is not dropped.
+        let inner: *mut Ed25519IdentityInner = &mut *slot;
+        let secret = unsafe {
+            drop_in_place(inner);
+            ManuallyDrop::new(read(addr_of!((*inner).key_pair.secret)))
+        };
+        assert_eq!(secret.as_bytes(), &[0; 32]);
+    }
+
+    #[test]
+    fn slip10_master_key() {
+        // Test vector 1 of SLIP-0010 for Ed25519.
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        assert_eq!(
+            hex::encode(*super::slip10_master_key(&seed)),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+    }
+
     #[test]
     fn eddsa_256_sign_verify() {
         let id = eddsa_identity();
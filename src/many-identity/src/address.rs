@@ -1,24 +1,89 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt::{Debug, Formatter};
+use core::str::FromStr;
 use many_error::ManyError;
 use sha3::digest::generic_array::typenum::Unsigned;
 use sha3::digest::OutputSizeUser;
 use sha3::Sha3_224;
-use std::convert::TryFrom;
-use std::fmt::{Debug, Formatter};
-use std::str::FromStr;
+
+#[cfg(feature = "csv")]
+mod csv;
+
+#[cfg(feature = "diesel")]
+mod sql;
 
 #[cfg(feature = "minicbor")]
 mod minicbor;
+#[cfg(feature = "minicbor")]
+pub use self::minicbor::{
+    decode_address_array_lenient, decode_address_set_canonical, encode_address_set_canonical,
+    AddressEncoding,
+};
+#[cfg(all(feature = "minicbor", feature = "coset"))]
+pub use self::minicbor::{verify_message_stream, MessageStream};
 
 #[cfg(feature = "serde")]
 mod serde;
 
+mod bounded;
+pub use bounded::BoundedAddress;
+
+mod destination;
+pub use destination::Destination;
+
+mod network;
+pub use network::NetworkId;
+
+mod qr;
+pub use qr::{QrMode, QrSegment};
+
+mod scoped;
+pub use scoped::ScopedAddress;
+
+mod emoji;
+mod wordlist;
+
 /// Subresource IDs are 31 bit integers.
 pub const MAX_SUBRESOURCE_ID: u32 = 0x7FFF_FFFF;
 
+/// The subresource IDs reserved for guardians (see
+/// [Address::guardian_subresource]); the highest 256 IDs.
+pub const GUARDIAN_SUBRESOURCE_IDS: core::ops::RangeInclusive<u32> =
+    (MAX_SUBRESOURCE_ID - 0xFF)..=MAX_SUBRESOURCE_ID;
+
+/// The character used to pad textual addresses to a fixed width. It is not
+/// part of the base32 alphabet nor the prefix, so it can always be trimmed
+/// safely.
+pub const TEXTUAL_PADDING_CHAR: char = '_';
+
+/// The key of the OpenTelemetry attribute created by [Address::otel_attribute].
+#[cfg(feature = "otel")]
+pub const OTEL_ATTRIBUTE_KEY: &str = "many.identity";
+
 const MAX_IDENTITY_BYTE_LEN: usize = 32;
+/// The version in the header of [Address::to_tagged_binary].
+const TAGGED_BINARY_VERSION: u8 = 0;
+/// Lowercase RFC4648 base32 alphabet used by the textual form.
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
 const SHA_OUTPUT_SIZE: usize = <Sha3_224 as OutputSizeUser>::OutputSize::USIZE;
 pub type PublicKeyHash = [u8; SHA_OUTPUT_SIZE];
 
+/// The byte lengths of each kind of address; a kind byte, then the public key
+/// hash, then the lower 3 bytes of the subresource ID (the upper byte is in
+/// the kind byte).
+const ANONYMOUS_BYTE_LEN: usize = 1;
+const PUBLIC_KEY_BYTE_LEN: usize = 1 + SHA_OUTPUT_SIZE;
+const SUBRESOURCE_BYTE_LEN: usize = PUBLIC_KEY_BYTE_LEN + 3;
+
+/// The length of [Address::to_order_preserving_key]; a kind byte, the public
+/// key hash and the subresource ID.
+pub const ORDER_PRESERVING_KEY_LEN: usize = 1 + SHA_OUTPUT_SIZE + 4;
+
 /// A subresource ID. Addresses with this must be of type 0x80-0xFF.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 #[must_use]
@@ -86,9 +151,65 @@ impl From<SubresourceId> for u32 {
     }
 }
 
+/// The kind of an address, as returned by [Address::kind].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AddressKind {
+    Anonymous,
+    PublicKey,
+    Subresource(u32),
+}
+
+impl core::fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AddressKind::Anonymous => f.write_str("anonymous"),
+            AddressKind::PublicKey => f.write_str("public-key"),
+            AddressKind::Subresource(id) => write!(f, "subresource({})", id),
+        }
+    }
+}
+
+/// How far [Address::parse_progress] got parsing a partial textual address.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseProgress {
+    /// Nothing was entered yet.
+    Empty,
+
+    /// The input is a valid start of a textual address, but incomplete.
+    PrefixOk,
+
+    /// The body decodes to an identity, but its checksum is incomplete.
+    BodyDecodes(Address),
+
+    /// The input is a complete and valid textual address.
+    ChecksumOk(Address),
+
+    /// The input cannot become a valid textual address.
+    Invalid(ManyError),
+}
+
+/// The result of [Address::diagnose_textual].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TextualDiagnosis {
+    /// The textual address is valid.
+    Valid(Address),
+
+    /// The body of the textual address decodes to this address, but the
+    /// checksum does not match it.
+    BadChecksumButBodyValid(Address),
+
+    /// The textual address cannot be decoded.
+    BadBody,
+}
+
 /// An identity address in the ManyVerse. This could be a server, network, user, DAO,
 /// automated process, etc.
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(::diesel::expression::AsExpression, ::diesel::deserialize::FromSqlRow),
+    diesel(sql_type = ::diesel::sql_types::Binary)
+)]
 #[must_use]
 pub struct Address(InnerAddress);
 
@@ -97,6 +218,68 @@ impl Address {
         InnerAddress::try_from(bytes).map(Self)
     }
 
+    /// Like [Address::from_bytes], but normalizes anonymous and public key
+    /// addresses followed by zero padding instead of rejecting them. The
+    /// strict [Address::from_bytes] (also used when decoding CBOR) only
+    /// accepts `[0]` as anonymous. Padding cannot extend past the 32 bytes of
+    /// the fixed-size form (see [Address::from_fixed32]).
+    pub fn from_bytes_lenient(bytes: &[u8]) -> Result<Self, ManyError> {
+        if bytes.len() > MAX_IDENTITY_BYTE_LEN {
+            return Err(ManyError::invalid_identity());
+        }
+        let is_padding = |rest: &[u8]| rest.iter().all(|b| *b == 0);
+        match bytes.first() {
+            Some(0) if is_padding(&bytes[1..]) => Ok(Self::anonymous()),
+            Some(1)
+                if bytes.len() > SHA_OUTPUT_SIZE && is_padding(&bytes[1 + SHA_OUTPUT_SIZE..]) =>
+            {
+                Self::from_bytes(&bytes[..=SHA_OUTPUT_SIZE])
+            }
+            _ => Self::from_bytes(bytes),
+        }
+    }
+
+    /// Like [Address::from_bytes], but calls `on_unknown` with the kind byte
+    /// and the bytes following it when the kind is unknown (`2..=0x7F`), e.g.
+    /// to map legacy kinds to addresses. If it returns `None` the kind is
+    /// rejected as invalid.
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        on_unknown: impl FnOnce(u8, &[u8]) -> Option<Address>,
+    ) -> Result<Self, ManyError> {
+        match bytes.split_first() {
+            Some((&kind @ 2..=0x7F, rest)) => on_unknown(kind, rest)
+                .ok_or_else(|| ManyError::invalid_identity_kind(kind.to_string())),
+            _ => Self::from_bytes(bytes),
+        }
+    }
+
+    /// Reads a fixed 32 bytes field, where the anonymous and public key
+    /// forms are padded with zeroes. Unlike [Address::from_bytes], this
+    /// accepts a padded public key. See also `TryFrom<&[u8; 32]>`.
+    pub fn from_fixed32(bytes: [u8; MAX_IDENTITY_BYTE_LEN]) -> Result<Self, ManyError> {
+        Self::try_from(&bytes)
+    }
+
+    /// Whether `s` is the textual anonymous address, ignoring surrounding
+    /// whitespace. This does not parse `s`, so the legacy `maaaa` form is not
+    /// recognized.
+    pub fn is_anonymous_str(s: &str) -> bool {
+        s.trim() == "maa"
+    }
+
+    /// Parses the hex encoding of the bytes of [Address::to_vec].
+    pub fn from_hex(s: &str) -> Result<Self, ManyError> {
+        let bytes = hex::decode(s).map_err(|_| ManyError::invalid_identity())?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Returns the hex encoding of [Address::to_vec], e.g. `00` for
+    /// anonymous.
+    pub fn to_hex(self) -> String {
+        hex::encode(self.to_vec())
+    }
+
     pub const fn anonymous() -> Self {
         Self(InnerAddress::anonymous())
     }
@@ -116,11 +299,47 @@ impl Address {
         self.0.is_subresource()
     }
 
+    /// Whether the kind byte of this address is in the reserved range
+    /// (`2..=0x7F`). Such addresses are rejected by every decoder, and can
+    /// only be built with the unchecked constructors of this crate.
+    #[inline]
+    pub const fn is_illegal(&self) -> bool {
+        self.0.is_illegal()
+    }
+
+    /// The kind of this address, to branch on it with a single `match`.
+    /// Returns `None` if the kind byte is reserved, which is only possible
+    /// with unchecked bytes (see [Address::is_illegal]).
+    pub const fn kind(&self) -> Option<AddressKind> {
+        match self.0.bytes[0] {
+            0 => Some(AddressKind::Anonymous),
+            1 => Some(AddressKind::PublicKey),
+            _ => match self.subresource_id() {
+                Some(id) => Some(AddressKind::Subresource(id)),
+                None => None,
+            },
+        }
+    }
+
     #[inline]
     pub const fn subresource_id(&self) -> Option<u32> {
         self.0.subresource_id()
     }
 
+    /// Like [Address::subresource_id], but for addresses received through
+    /// unchecked routes; returns an invalid kind error if the kind byte is
+    /// reserved, and an invalid subresource error for anonymous and public
+    /// key addresses.
+    pub fn subresource_id_checked(&self) -> Result<u32, ManyError> {
+        if self.is_illegal() {
+            return Err(ManyError::invalid_identity_kind(
+                self.0.bytes[0].to_string(),
+            ));
+        }
+        self.subresource_id()
+            .ok_or_else(ManyError::invalid_identity_subid)
+    }
+
     #[inline]
     pub fn with_subresource_id<I: TryInto<SubresourceId, Error = ManyError>>(
         &self,
@@ -129,6 +348,28 @@ impl Address {
         Ok(self.with_subresource_id_unchecked(subid.try_into()?))
     }
 
+    /// Whether this is a subresource whose ID fits in `bits` bits, for
+    /// protocols that only reserve part of the subresource ID range.
+    pub fn subresource_id_fits(&self, bits: u32) -> bool {
+        self.subresource_id()
+            .map_or(false, |id| id.checked_shr(bits).map_or(true, |x| x == 0))
+    }
+
+    /// Like [Address::with_subresource_id], but errors if the ID does not fit
+    /// in `bits` bits.
+    pub fn with_subresource_id_bounded<I: TryInto<SubresourceId, Error = ManyError>>(
+        &self,
+        subid: I,
+        bits: u32,
+    ) -> Result<Self, ManyError> {
+        let subid = subid.try_into()?;
+        if subid.0.checked_shr(bits).map_or(true, |x| x == 0) {
+            Ok(self.with_subresource_id_unchecked(subid))
+        } else {
+            Err(ManyError::invalid_identity_subid())
+        }
+    }
+
     #[inline]
     pub const fn with_subresource_id_unchecked(&self, subid: SubresourceId) -> Self {
         if let Some(h) = self.0.hash() {
@@ -138,6 +379,69 @@ impl Address {
         }
     }
 
+    /// The subresources of this address with IDs in `range`, e.g. to
+    /// provision many subresources of one account. IDs above
+    /// [MAX_SUBRESOURCE_ID] are skipped, and anonymous has no subresources.
+    pub fn subresources(&self, range: core::ops::Range<u32>) -> impl Iterator<Item = Self> {
+        let end = range.end.min(MAX_SUBRESOURCE_ID + 1);
+        let ids = range.start..end;
+        self.0.hash().into_iter().flat_map(move |h| {
+            ids.clone()
+                .map(move |id| Self(InnerAddress::subresource_unchecked(h, SubresourceId(id))))
+        })
+    }
+
+    /// Returns the subresource whose ID is made of a 15 bits `region` (its
+    /// highest bit is ignored) and a 16 bits `seq`.
+    pub fn with_subresource_parts(&self, region: u16, seq: u16) -> Result<Self, ManyError> {
+        self.with_subresource_id((((region & 0x7FFF) as u32) << 16) | seq as u32)
+    }
+
+    /// Splits the subresource ID into the region and sequence of
+    /// [Address::with_subresource_parts].
+    pub const fn subresource_parts(&self) -> Option<(u16, u16)> {
+        match self.subresource_id() {
+            Some(id) => Some(((id >> 16) as u16, (id & 0xFFFF) as u16)),
+            None => None,
+        }
+    }
+
+    /// Returns the subresource of a guardian, e.g. for social recovery. These
+    /// are in [GUARDIAN_SUBRESOURCE_IDS] so they do not collide with normal
+    /// accounts. Returns an invalid identity error for addresses without a
+    /// public key hash (e.g. anonymous), which cannot have guardians.
+    pub fn guardian_subresource(&self, guardian_index: u8) -> Result<Self, ManyError> {
+        let parent = self.parent().ok_or_else(ManyError::invalid_identity)?;
+        parent.with_subresource_id(GUARDIAN_SUBRESOURCE_IDS.start() + guardian_index as u32)
+    }
+
+    /// The public key address of a subresource, or the address itself if it
+    /// is a public key. Anonymous has no parent.
+    pub const fn parent(&self) -> Option<Self> {
+        match self.0.hash() {
+            Some(hash) => Some(Self(InnerAddress::public_key(hash))),
+            None => None,
+        }
+    }
+
+    /// By convention, the subresource 0 of a public key is its default
+    /// account. Subresources are their own account, and anonymous has none
+    /// (it returns itself).
+    #[inline]
+    pub const fn default_account(&self) -> Self {
+        if self.is_public_key() {
+            self.with_subresource_id_unchecked(SubresourceId(0))
+        } else {
+            *self
+        }
+    }
+
+    /// Whether this is the default account (subresource 0) of a public key.
+    #[inline]
+    pub const fn is_default_account(&self) -> bool {
+        matches!(self.subresource_id(), Some(0))
+    }
+
     #[inline]
     pub const fn can_sign(&self) -> bool {
         self.is_public_key() || self.is_subresource()
@@ -148,21 +452,65 @@ impl Address {
         self.is_anonymous() || self.is_public_key() || self.is_subresource()
     }
 
+    /// Like [Address::can_be_source], but also rejects addresses that could
+    /// only be built with unchecked constructors; illegal kind bytes, or
+    /// non-zero padding bytes after an anonymous or public key address.
+    #[inline]
+    pub const fn is_valid_source(&self) -> bool {
+        self.can_be_source() && self.0.is_canonical()
+    }
+
     #[inline]
     pub const fn can_be_dest(&self) -> bool {
         self.is_public_key() || self.is_subresource()
     }
 
+    /// Like [Address::can_be_dest], but returns an error explaining why
+    /// this address cannot be a destination.
+    pub fn check_dest(&self) -> Result<(), ManyError> {
+        if self.can_be_dest() {
+            Ok(())
+        } else if self.is_anonymous() {
+            Err(ManyError::anonymous_cannot_be_destination())
+        } else {
+            Err(ManyError::invalid_identity_kind(
+                self.0.bytes[0].to_string(),
+            ))
+        }
+    }
+
     #[inline]
     pub fn to_vec(self) -> Vec<u8> {
         self.0.to_vec()
     }
 
     #[inline]
-    pub fn to_byte_array(self) -> [u8; MAX_IDENTITY_BYTE_LEN] {
+    pub const fn to_byte_array(self) -> [u8; MAX_IDENTITY_BYTE_LEN] {
         self.0.to_byte_array()
     }
 
+    /// The same bytes as [Address::to_vec], borrowed from the address instead
+    /// of allocated.
+    #[inline]
+    pub fn as_significant_bytes(&self) -> &[u8] {
+        &self.0.bytes[..self.0.significant_len()]
+    }
+
+    /// Iterate over the bits of the 32 bytes array of this address, most
+    /// significant bit first. Useful for building radix trees.
+    pub fn bits(&self) -> impl Iterator<Item = bool> {
+        let bytes = self.to_byte_array();
+        (0..MAX_IDENTITY_BYTE_LEN * 8).map(move |i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+    }
+
+    /// Returns the bit at index `i` in the 32 bytes array of this address
+    /// (most significant bit first), or None if out of range.
+    pub fn bit(&self, i: usize) -> Option<bool> {
+        self.to_byte_array()
+            .get(i / 8)
+            .map(|b| b & (0x80 >> (i % 8)) != 0)
+    }
+
     /// Check that another identity matches this one, ignoring any subresouce IDs.
     #[inline]
     pub fn matches(&self, other: &Address) -> bool {
@@ -170,8 +518,155 @@ impl Address {
             other.is_anonymous()
         } else {
             // Extract public key hash of both.
-            self.0.hash() == other.0.hash()
+            match (self.0.hash(), other.0.hash()) {
+                (Some(a), Some(b)) => ct_eq(&a, &b),
+                _ => false,
+            }
+        }
+    }
+
+    /// The canonical encoding of this address in protobuf `bytes` fields.
+    /// This is the untagged, minimized form returned by [Address::to_vec]
+    /// (unlike CBOR, there is no tag).
+    pub fn to_proto_bytes(self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    /// Decodes an address from a protobuf `bytes` field, encoded by
+    /// [Address::to_proto_bytes].
+    pub fn from_proto_bytes(bytes: &[u8]) -> Result<Self, ManyError> {
+        Self::from_bytes(bytes)
+    }
+
+    /// The binary form of this address prefixed by a header byte, with the
+    /// format version in the high 4 bits and the kind of address in the low
+    /// 4 bits (0 for anonymous, 1 for a public key and 2 for a subresource).
+    /// Only version 0 exists, where the payload is [Address::to_vec].
+    /// Reserved kinds have no header kind and return an invalid kind error.
+    pub fn to_tagged_binary(self) -> Result<Vec<u8>, ManyError> {
+        let mut bytes = vec![(TAGGED_BINARY_VERSION << 4) | self.tagged_binary_kind()?];
+        bytes.extend_from_slice(self.as_significant_bytes());
+        Ok(bytes)
+    }
+
+    /// Decodes an address encoded by [Address::to_tagged_binary], refusing
+    /// unknown versions and headers whose kind does not match the payload.
+    pub fn from_tagged_binary(bytes: &[u8]) -> Result<Self, ManyError> {
+        let (header, payload) = bytes
+            .split_first()
+            .ok_or_else(ManyError::invalid_identity)?;
+        let (version, kind) = (header >> 4, header & 0x0F);
+        if version != TAGGED_BINARY_VERSION {
+            return Err(ManyError::unknown(format!(
+                "Unsupported identity header version: {}",
+                version
+            )));
+        }
+
+        let address = Self::from_bytes(payload)?;
+        if kind != address.tagged_binary_kind()? {
+            return Err(ManyError::invalid_identity_kind(kind.to_string()));
+        }
+        Ok(address)
+    }
+
+    fn tagged_binary_kind(&self) -> Result<u8, ManyError> {
+        match self.kind() {
+            Some(AddressKind::Anonymous) => Ok(0),
+            Some(AddressKind::PublicKey) => Ok(1),
+            Some(AddressKind::Subresource(_)) => Ok(2),
+            None => Err(ManyError::invalid_identity_kind(
+                self.0.bytes[0].to_string(),
+            )),
+        }
+    }
+
+    /// Split the uppercase textual form of this identity into QR code
+    /// segments. Textual identities only use base32 characters, so this is
+    /// normally a single alphanumeric segment, which is denser than byte mode.
+    pub fn to_qr_segments(self) -> Vec<QrSegment> {
+        qr::segments(&self.to_string().to_uppercase())
+    }
+
+    /// Returns a compact value for recording this identity in a `tracing`
+    /// field, e.g. `subresource(1):mahukzwu..wijp`.
+    pub fn as_tracing_field(&self) -> impl tracing::Value {
+        tracing::field::display(TracingField(*self))
+    }
+
+    /// Returns the textual form of this identity as an OpenTelemetry value.
+    #[cfg(feature = "otel")]
+    pub fn otel_value(&self) -> opentelemetry::Value {
+        self.to_string().into()
+    }
+
+    /// Returns this identity as an OpenTelemetry attribute, keyed by
+    /// [OTEL_ATTRIBUTE_KEY].
+    #[cfg(feature = "otel")]
+    pub fn otel_attribute(&self) -> opentelemetry::KeyValue {
+        opentelemetry::KeyValue::new(OTEL_ATTRIBUTE_KEY, self.otel_value())
+    }
+
+    /// Check that a public COSE key matches this identity, ignoring any
+    /// subresource IDs. Anonymous only matches the absence of a key.
+    #[cfg(feature = "coset")]
+    pub fn matches_key(&self, key: Option<&coset::CoseKey>) -> bool {
+        match key {
+            None => self.is_anonymous(),
+            Some(key) => crate::cose::address_unchecked(key).map_or(false, |a| self.matches(&a)),
+        }
+    }
+
+    /// Returns the address of a public COSE key, taking ownership of it. Like
+    /// [crate::cose::address_unchecked], this DOES NOT VERIFY that the key
+    /// only contains a public key.
+    #[cfg(feature = "coset")]
+    pub fn from_cose_key_owned(key: coset::CoseKey) -> Result<Self, ManyError> {
+        use coset::CborSerializable;
+        use sha3::Digest;
+
+        let pk = Sha3_224::digest(
+            key.to_vec()
+                .map_err(|e| ManyError::unknown(e.to_string()))?,
+        );
+        Ok(Self::public_key_unchecked(pk.into()))
+    }
+
+    /// Like [Address::matches_key], but also returns the hash of the key so
+    /// callers can cache it for [Address::matches_key_hash].
+    #[cfg(feature = "coset")]
+    pub fn matches_key_returning_hash(
+        &self,
+        key: &coset::CoseKey,
+    ) -> Result<(bool, PublicKeyHash), ManyError> {
+        let hash = crate::cose::address_unchecked(key)?
+            .0
+            .hash()
+            .ok_or_else(ManyError::invalid_identity)?;
+        Ok((self.matches_key_hash(&hash), hash))
+    }
+
+    /// Check that the hash of a public key matches this identity, ignoring any
+    /// subresource IDs.
+    pub fn matches_key_hash(&self, hash: &PublicKeyHash) -> bool {
+        self.0.hash().map_or(false, |h| ct_eq(&h, hash))
+    }
+
+    /// Check that this identity is owned by `owner` (either the owner itself
+    /// or one of its subresources), and that `key` is the owner's key.
+    #[cfg(feature = "coset")]
+    pub fn authorize_under(
+        &self,
+        owner: &Address,
+        key: Option<&coset::CoseKey>,
+    ) -> Result<(), ManyError> {
+        if !self.matches(owner) {
+            return Err(ManyError::identity_mismatch(owner, self));
+        }
+        if !owner.matches_key(key) {
+            return Err(ManyError::public_key_mismatch(owner));
         }
+        Ok(())
     }
 
     /// Create an identity from the raw value of a public key hash, without checking
@@ -184,154 +679,840 @@ impl Address {
     /// many-identity-dsa) or in the testing utilities available here to create
     /// a bogus address.
     #[inline(always)]
-    pub fn public_key_unchecked(hash: PublicKeyHash) -> Self {
+    pub const fn public_key_unchecked(hash: PublicKeyHash) -> Self {
         Self(InnerAddress::public_key(hash))
     }
-}
 
-impl PartialEq<&str> for Address {
-    #[allow(clippy::cmp_owned)]
-    fn eq(&self, other: &&str) -> bool {
-        self.to_string() == *other
+    /// Returns the subresource `subid` of a public key hash, erroring if the
+    /// ID is above [MAX_SUBRESOURCE_ID] instead of losing its highest bit.
+    pub fn try_subresource(hash: PublicKeyHash, subid: u32) -> Result<Self, ManyError> {
+        Self::public_key_unchecked(hash).with_subresource_id(subid)
     }
-}
 
-impl PartialEq<Option<Address>> for Address {
-    fn eq(&self, other: &Option<Address>) -> bool {
-        match other {
-            Some(o) => o == self,
-            None => self.is_anonymous(),
+    /// Returns the textual form of this address, right-padded with
+    /// [TEXTUAL_PADDING_CHAR] to `width` characters. Returns an error if the
+    /// textual form is longer than `width`.
+    pub fn to_padded_string(self, width: usize) -> Result<String, ManyError> {
+        let mut s = self.to_string();
+        if s.len() > width {
+            return Err(ManyError::unknown(format!(
+                "Address is {} characters, longer than the padded width {width}.",
+                s.len()
+            )));
         }
+
+        s.extend(core::iter::repeat(TEXTUAL_PADDING_CHAR).take(width - s.len()));
+        Ok(s)
     }
-}
 
-impl PartialEq<Address> for Option<Address> {
-    fn eq(&self, other: &Address) -> bool {
-        match self {
-            Some(s) => other == s,
-            None => other.is_anonymous(),
+    /// Returns a key whose lexicographic byte order is the same as the order
+    /// of addresses (kind, then public key hash, then subresource ID). This is
+    /// meant to be used as a key in sorted key-value stores.
+    ///
+    /// The first byte is 0 for anonymous, 1 for public keys and 2 for
+    /// subresources, followed by the public key hash and the big endian
+    /// subresource ID (both zeroed when absent). Reserved kinds, which can
+    /// only be constructed unchecked, are 3 followed by their raw bytes.
+    pub fn to_order_preserving_key(self) -> [u8; ORDER_PRESERVING_KEY_LEN] {
+        self.0.order_key()
+    }
+
+    /// Like [Address::to_order_preserving_key], but without the zeroed
+    /// fields, for use as a custom collation in databases (e.g. SQLite)
+    /// comparing keys with `memcmp`. A shorter key sorts before any longer
+    /// key of the same prefix, so the order is unchanged.
+    pub fn collation_key(&self) -> Vec<u8> {
+        let len = match self.0.bytes[0] {
+            0 => 1,
+            1 => 1 + SHA_OUTPUT_SIZE,
+            _ => ORDER_PRESERVING_KEY_LEN,
+        };
+        self.0.order_key()[..len].to_vec()
+    }
+
+    /// Decodes a key created with [Address::to_order_preserving_key].
+    pub fn from_order_preserving_key(
+        key: &[u8; ORDER_PRESERVING_KEY_LEN],
+    ) -> Result<Self, ManyError> {
+        let mut hash = [0; SHA_OUTPUT_SIZE];
+        hash.copy_from_slice(&key[1..=SHA_OUTPUT_SIZE]);
+        let mut subid = [0; 4];
+        subid.copy_from_slice(&key[SHA_OUTPUT_SIZE + 1..]);
+        let subid = u32::from_be_bytes(subid);
+
+        match key[0] {
+            0 if key[1..].iter().all(|b| *b == 0) => Ok(Self::anonymous()),
+            1 if subid == 0 => Ok(Self::public_key_unchecked(hash)),
+            2 => Ok(Self(InnerAddress::subresource_unchecked(
+                hash,
+                subid.try_into()?,
+            ))),
+            _ => Err(ManyError::invalid_identity()),
         }
     }
-}
 
-impl Debug for Address {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("Identity")
-            .field(&if self.is_anonymous() {
-                "anonymous".to_string()
-            } else if self.is_public_key() {
-                "public-key".to_string()
-            } else if self.is_subresource() {
-                format!("subresource({})", self.subresource_id().unwrap_or_default())
-            } else {
-                "??".to_string()
+    /// Deterministically search for a subresource of this address whose
+    /// textual form (after the `m` prefix) starts with `prefix`, trying at
+    /// most `max_iters` subresource IDs. The first characters are mostly
+    /// determined by the highest bits of the subresource ID and the public
+    /// key hash, so the search varies the highest 7 bits of the ID first.
+    /// Only short prefixes are realistic.
+    pub fn find_vanity_subresource(&self, prefix: &str, max_iters: u32) -> Option<(u32, Self)> {
+        let hash = self.0.hash()?;
+        (0..max_iters.min(MAX_SUBRESOURCE_ID + 1))
+            .map(|i| ((i & 0x7F) << 24) | (i >> 7))
+            .map(|id| {
+                let address = Self(InnerAddress::subresource_unchecked(hash, SubresourceId(id)));
+                (id, address)
             })
-            .field(&self.to_string())
-            .finish()
+            .find(|(_, address)| address.to_string()[1..].starts_with(prefix))
     }
-}
 
-impl Default for Address {
-    fn default() -> Self {
-        Address::anonymous()
+    /// Groups identities by the public key owning them. Each public key in
+    /// `ids` and each subresource of that key is listed under the key, in the
+    /// order they appear in `ids`. Anonymous identities are listed under
+    /// [Address::anonymous].
+    pub fn group_by_key(ids: &[Address]) -> BTreeMap<Address, Vec<Address>> {
+        let mut groups: BTreeMap<Address, Vec<Address>> = BTreeMap::new();
+        for id in ids {
+            let key = id.parent().unwrap_or_else(Self::anonymous);
+            groups.entry(key).or_default().push(*id);
+        }
+        groups
     }
-}
 
-impl std::fmt::Display for Address {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0.to_string())
+    /// Returns the subresources in `others` of the same key as this
+    /// subresource whose ID is at most `within` away from this one, with the
+    /// difference. This is useful to flag possible typos. This address itself
+    /// and identities that are not subresources of the same key are ignored.
+    pub fn nearby_subresource_ids(&self, others: &[Address], within: u32) -> Vec<(Self, u32)> {
+        let id = match self.subresource_id() {
+            Some(id) => id,
+            None => return vec![],
+        };
+
+        others
+            .iter()
+            .filter(|other| *other != self && other.matches(self))
+            .filter_map(|other| {
+                let diff = other.subresource_id()?.abs_diff(id);
+                (diff <= within).then(|| (*other, diff))
+            })
+            .collect()
     }
-}
 
-impl TryFrom<&[u8]> for Address {
-    type Error = ManyError;
+    /// A pseudonymous alias of this address that is the same within an epoch
+    /// but cannot be linked across epochs, e.g. for rate limiting without
+    /// long-term tracking. This is the first 16 bytes of the SHA3-256 of the
+    /// address bytes followed by the big endian epoch.
+    pub fn epoch_alias(&self, epoch: u64) -> [u8; 16] {
+        use sha3::{Digest, Sha3_256};
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.as_significant_bytes());
+        hasher.update(epoch.to_be_bytes());
+        let mut alias = [0; 16];
+        alias.copy_from_slice(&hasher.finalize()[..16]);
+        alias
+    }
 
-    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        Self::from_bytes(bytes)
+    /// Returns `k` hashes of this address to insert into or look up in a
+    /// bloom filter. The first two 64-bit words of the SHA3-256 of the address
+    /// bytes are combined with double hashing, `h1 + i * h2`. `h2` is made
+    /// odd so the hashes do not repeat over a power of two filter size.
+    pub fn bloom_hashes(&self, k: usize) -> Vec<u64> {
+        use sha3::{Digest, Sha3_256};
+
+        let digest = Sha3_256::digest(self.as_significant_bytes());
+        let mut word = [0; 8];
+        word.copy_from_slice(&digest[..8]);
+        let h1 = u64::from_be_bytes(word);
+        word.copy_from_slice(&digest[8..16]);
+        let h2 = u64::from_be_bytes(word) | 1;
+
+        (0..k as u64)
+            .map(|i| h1.wrapping_add(i.wrapping_mul(h2)))
+            .collect()
     }
-}
 
-impl TryFrom<String> for Address {
-    type Error = ManyError;
+    /// Returns a commitment to this address that hides its subresource ID
+    /// until the blinding factor is disclosed. This is
+    /// `Sha3_224(hash ++ subid_be ++ blinding)`. A public key is committed as
+    /// its default account (subresource 0), and anonymous uses an all-zero
+    /// hash.
+    pub fn subresource_commitment(&self, blinding: &[u8; 32]) -> PublicKeyHash {
+        use sha3::Digest;
+
+        let hash = self.0.hash().unwrap_or_default();
+        let subid = self.subresource_id().unwrap_or(0);
+
+        let mut hasher = Sha3_224::new();
+        hasher.update(hash);
+        hasher.update(subid.to_be_bytes());
+        hasher.update(blinding);
+        hasher.finalize().into()
+    }
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        InnerAddress::try_from(value).map(Self)
+    /// Verifies a commitment created by [Address::subresource_commitment].
+    pub fn verify_subresource_commitment(
+        &self,
+        commitment: &PublicKeyHash,
+        blinding: &[u8; 32],
+    ) -> bool {
+        ct_eq(&self.subresource_commitment(blinding), commitment)
     }
-}
 
-impl FromStr for Address {
-    type Err = ManyError;
+    /// Returns two `#rrggbb` colors derived from this address, e.g. for the
+    /// gradient background of an avatar. The same address always returns
+    /// the same colors.
+    pub fn avatar_colors(&self) -> (String, String) {
+        use sha3::{Digest, Sha3_256};
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        InnerAddress::from_str(s).map(Self)
+        let digest = Sha3_256::digest(self.as_significant_bytes());
+        (
+            format!("#{}", hex::encode(&digest[0..3])),
+            format!("#{}", hex::encode(&digest[3..6])),
+        )
     }
-}
 
-impl AsRef<[u8; MAX_IDENTITY_BYTE_LEN]> for Address {
-    fn as_ref(&self) -> &[u8; MAX_IDENTITY_BYTE_LEN] {
-        let result: &[u8; MAX_IDENTITY_BYTE_LEN] = unsafe { std::mem::transmute(self) };
-        result
+    /// Returns a numeric code of `digits` digits derived from this address,
+    /// so two people can read it aloud to confirm they have the same address.
+    /// This is a confirmation aid, not a security primitive; short codes
+    /// collide easily.
+    pub fn verification_code(&self, digits: usize) -> String {
+        use core::fmt::Write;
+        use sha3::{Digest, Sha3_256};
+
+        let mut code = String::with_capacity(digits);
+        let mut digest = Sha3_256::digest(self.as_significant_bytes());
+        while code.len() < digits {
+            // 18 digits always fit in a u64.
+            let n = (digits - code.len()).min(18);
+            let mut value = [0u8; 8];
+            value.copy_from_slice(&digest[..8]);
+            let value = u64::from_be_bytes(value) % 10u64.pow(n as u32);
+            // Writing to a String cannot fail.
+            let _ = write!(code, "{:0width$}", value, width = n);
+            digest = Sha3_256::digest(digest);
+        }
+        code
     }
-}
-
-#[derive(Copy, Clone, Eq, Debug, Ord, PartialOrd)]
-#[non_exhaustive]
-#[must_use]
-struct InnerAddress {
-    bytes: [u8; MAX_IDENTITY_BYTE_LEN],
-}
 
-// Identity needs to be bound to 32 bytes maximum.
-static_assertions::assert_eq_size!([u8; MAX_IDENTITY_BYTE_LEN], InnerAddress);
-static_assertions::const_assert_eq!(InnerAddress::anonymous().to_byte_array()[0], 0);
+    /// Returns `count` words from the BIP39 English wordlist derived from this
+    /// address. The same address always returns the same words, so they can
+    /// be shown to users as a visual check that they entered the right
+    /// address. This is not a mnemonic and cannot be used to recover the
+    /// address.
+    pub fn safety_words(&self, count: usize) -> Vec<String> {
+        use sha3::{Digest, Sha3_256};
+
+        // Each word takes 11 bits of a SHA3-256 digest of the address bytes.
+        // When running out of bits, hash the digest again.
+        const WORDS_PER_DIGEST: usize = 256 / 11;
+
+        let mut words = Vec::with_capacity(count);
+        let mut digest = Sha3_256::digest(self.as_significant_bytes());
+        while words.len() < count {
+            for i in 0..WORDS_PER_DIGEST.min(count - words.len()) {
+                let offset = i * 11;
+                let byte = |j: usize| digest.get(offset / 8 + j).copied().unwrap_or(0) as u32;
+                let bits = (byte(0) << 16) | (byte(1) << 8) | byte(2);
+                let index = (bits >> (24 - 11 - offset % 8)) & 0x7FF;
+                words.push(wordlist::BIP39_ENGLISH[index as usize].to_string());
+            }
+            digest = Sha3_256::digest(digest);
+        }
+        words
+    }
 
-impl PartialEq for InnerAddress {
-    fn eq(&self, other: &Self) -> bool {
-        match (&self.bytes[0], &other.bytes[0]) {
-            // Anonymous
-            (0, 0) => true,
+    /// Returns `count` emoji derived from this address, for wallets that show
+    /// addresses as emoji for a quick visual comparison. Like
+    /// [Address::safety_words], equal addresses return the same sequence.
+    pub fn to_emoji(self, count: usize) -> String {
+        use sha3::{Digest, Sha3_256};
+
+        // Each emoji takes one byte of a SHA3-256 digest of the address
+        // bytes. When running out of bytes, hash the digest again.
+        let mut emoji = String::new();
+        let mut digest = Sha3_256::digest(self.as_significant_bytes());
+        let mut remaining = count;
+        while remaining > 0 {
+            let n = remaining.min(digest.len());
+            for byte in &digest[..n] {
+                emoji.push_str(emoji::EMOJI[*byte as usize]);
+            }
+            remaining -= n;
+            digest = Sha3_256::digest(digest);
+        }
+        emoji
+    }
 
-            // Public Key
-            (1, 1) => self.bytes[1..=SHA_OUTPUT_SIZE] == other.bytes[1..=SHA_OUTPUT_SIZE],
+    /// Writes the textual form of this address into a writer, without
+    /// building an intermediate `String`.
+    pub fn write_textual<W: core::fmt::Write>(&self, w: &mut W) -> core::fmt::Result {
+        self.0.write_textual(w)
+    }
 
-            // Subresource
-            (x @ 0x80..=0xFF, y @ 0x80..=0xFF) if x == y => self.bytes[1..] == other.bytes[1..],
+    /// Parses an address padded by [Address::to_padded_string].
+    pub fn from_padded_string(s: &str) -> Result<Self, ManyError> {
+        Self::from_str(s.trim_end_matches(TEXTUAL_PADDING_CHAR))
+    }
 
-            // Anything else if by default inequal.
-            (_, _) => false,
+    /// Parses an address optionally followed by a subresource ID suffix,
+    /// e.g. `m...@1234` or `m...@0x4d2`. The suffix is applied as a
+    /// subresource of the parsed address. Anonymous has no subresources, so
+    /// a suffix on it is an error.
+    pub fn from_str_with_subresource_suffix(s: &str) -> Result<Self, ManyError> {
+        let (address, suffix) = match s.split_once('@') {
+            Some((address, suffix)) => (address, Some(suffix)),
+            None => (s, None),
+        };
+        let address = Self::from_str(address)?;
+
+        match suffix {
+            None => Ok(address),
+            Some(_) if address.is_anonymous() => Err(ManyError::invalid_identity_subid()),
+            Some(suffix) => {
+                let id = match suffix
+                    .strip_prefix("0x")
+                    .or_else(|| suffix.strip_prefix("0X"))
+                {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => suffix.parse::<u64>(),
+                }
+                .map_err(|_| ManyError::invalid_identity_subid())?;
+                address.with_subresource_id(id)
+            }
         }
     }
-}
 
-impl Default for InnerAddress {
-    fn default() -> Self {
-        InnerAddress::anonymous()
+    /// Formats this address as the public key address followed by a decimal
+    /// `@<id>` suffix if it is a subresource. This is the inverse of
+    /// [Address::from_str_with_subresource_suffix].
+    pub fn to_str_with_subresource_suffix(self) -> String {
+        match (self.parent(), self.subresource_id()) {
+            (Some(parent), Some(id)) => format!("{}@{}", parent, id),
+            _ => self.to_string(),
+        }
     }
-}
 
-impl InnerAddress {
-    pub const fn anonymous() -> Self {
-        Self {
-            bytes: [0; MAX_IDENTITY_BYTE_LEN],
+    /// Tells whether a textual address is valid, or only its checksum is
+    /// wrong (e.g. the last two characters were mistyped), or it cannot be
+    /// decoded at all.
+    pub fn diagnose_textual(s: &str) -> TextualDiagnosis {
+        if let Ok(address) = Self::from_str(s) {
+            return TextualDiagnosis::Valid(address);
+        }
+        if !s.starts_with('m') || s.len() < 3 {
+            return TextualDiagnosis::BadBody;
+        }
+
+        match InnerAddress::from_str_body(s) {
+            // Only the checksum differs if re-encoding gives the same body.
+            Ok(inner) if inner.to_string().get(..s.len() - 2) == s.get(..s.len() - 2) => {
+                TextualDiagnosis::BadChecksumButBodyValid(Self(inner))
+            }
+            _ => TextualDiagnosis::BadBody,
         }
     }
 
-    pub const fn public_key(hash: [u8; SHA_OUTPUT_SIZE]) -> Self {
-        let mut bytes = [0; MAX_IDENTITY_BYTE_LEN];
-        bytes[0] = 1;
-        let mut len = SHA_OUTPUT_SIZE;
-        while len > 0 {
-            len -= 1;
-            bytes[1 + len] = hash[len];
+    /// If only the checksum of a textual address is wrong, returns the
+    /// address with the correct checksum (e.g. for a "did you mean ...?"
+    /// prompt). Returns `None` if the address is valid or cannot be decoded.
+    pub fn repair_checksum(s: &str) -> Option<String> {
+        match Self::diagnose_textual(s) {
+            TextualDiagnosis::BadChecksumButBodyValid(address) => Some(address.to_string()),
+            _ => None,
         }
-        Self { bytes }
     }
 
-    pub(crate) const fn subresource_unchecked(
+    /// Reads an address from the environment variable `var`. Surrounding
+    /// whitespace is ignored and the address can be in uppercase. The error
+    /// names the variable if it is missing or invalid.
+    #[cfg(feature = "std")]
+    pub fn from_env(var: &str) -> Result<Self, ManyError> {
+        let value = std::env::var(var).map_err(|e| {
+            ManyError::unknown(format!(
+                "Could not read environment variable {}: {}",
+                var, e
+            ))
+        })?;
+        Self::from_str_normalized(&value)
+            .map(|(address, _)| address)
+            .map_err(|e| {
+                ManyError::unknown(format!(
+                    "Environment variable {} is not a valid identity: {}",
+                    var, e
+                ))
+            })
+    }
+
+    /// Parses an address after trimming whitespace and lowercasing it. The
+    /// boolean is true if the input had to be normalized, so callers can warn
+    /// that it was not in canonical form.
+    pub fn from_str_normalized(s: &str) -> Result<(Self, bool), ManyError> {
+        let normalized = s.trim().to_ascii_lowercase();
+        let address = Self::from_str(&normalized)?;
+        Ok((address, normalized != s))
+    }
+
+    /// Parses a textual address like [`FromStr`], but reports a body that
+    /// decodes correctly with a wrong checksum as
+    /// [`ManyError::invalid_identity_checksum`] instead of a generic invalid
+    /// identity, so callers can tell a typo from garbage.
+    pub fn from_str_checked(s: &str) -> Result<Self, ManyError> {
+        InnerAddress::from_str_checked(s).map(Self)
+    }
+
+    /// The 2 checksum characters at the end of the textual form of this
+    /// address.
+    pub fn checksum(&self) -> [char; 2] {
+        self.0.checksum()
+    }
+
+    /// The CRC16 that the [checksum](Address::checksum) characters are
+    /// taken from (its highest 10 bits), for tools that store it as a number.
+    pub fn checksum_u16(&self) -> u16 {
+        self.0.crc16()
+    }
+
+    /// Parses a partial textual address, telling how far it is from being
+    /// valid. This is meant to give feedback while an address is typed.
+    pub fn parse_progress(s: &str) -> ParseProgress {
+        /// Base32 is 5 bits per character.
+        const MAX_TEXTUAL_LEN: usize = 1 + (MAX_IDENTITY_BYTE_LEN * 8 + 4) / 5 + 2;
+
+        let rest = match s.strip_prefix('m') {
+            _ if s.is_empty() => return ParseProgress::Empty,
+            Some(rest) => rest,
+            None => {
+                return ParseProgress::Invalid(ManyError::invalid_identity_prefix(
+                    s.chars().next().unwrap_or_default().to_string(),
+                ))
+            }
+        };
+        if let Ok(address) = Self::from_str(s) {
+            return ParseProgress::ChecksumOk(address);
+        }
+        if s.len() > MAX_TEXTUAL_LEN || !rest.chars().all(|c| matches!(c, 'a'..='z' | '2'..='7')) {
+            return ParseProgress::Invalid(ManyError::invalid_identity());
+        }
+        if let TextualDiagnosis::BadChecksumButBodyValid(_) = Self::diagnose_textual(s) {
+            return ParseProgress::Invalid(ManyError::invalid_identity());
+        }
+
+        // The body might be complete, with up to one checksum character.
+        for checksum_len in 0..=1 {
+            let body = match rest.get(..rest.len().saturating_sub(checksum_len)) {
+                Some(body) if body.len() + checksum_len == rest.len() => body,
+                _ => continue,
+            };
+            if let Ok(inner) = InnerAddress::from_base32(body) {
+                return if inner.to_string().starts_with(s) {
+                    ParseProgress::BodyDecodes(Self(inner))
+                } else {
+                    ParseProgress::Invalid(ManyError::invalid_identity())
+                };
+            }
+        }
+
+        ParseProgress::PrefixOk
+    }
+
+    /// Validates that `s` is the canonical textual form of an address and
+    /// returns it unchanged, without keeping the parsed address around.
+    pub fn validate_borrowed(s: &str) -> Result<&str, ManyError> {
+        if Self::from_str(s)? == s {
+            Ok(s)
+        } else {
+            Err(ManyError::invalid_identity())
+        }
+    }
+
+    /// Whether `s` is exactly the canonical textual form of this address;
+    /// lowercase, without surrounding whitespace, and `maa` for anonymous.
+    /// This is what `PartialEq<&str>` compares.
+    #[allow(clippy::cmp_owned)]
+    pub fn eq_str_canonical(&self, s: &str) -> bool {
+        self.to_string() == s
+    }
+
+    /// Whether `s` parses to this address after normalizing it like
+    /// [Address::from_str_normalized], so uppercase input or the legacy
+    /// `maaaa` anonymous form also match.
+    pub fn eq_str_lenient(&self, s: &str) -> bool {
+        matches!(Self::from_str_normalized(s), Ok((address, _)) if address == *self)
+    }
+}
+
+/// Strict, see [Address::eq_str_canonical] and [Address::eq_str_lenient].
+impl PartialEq<&str> for Address {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str_canonical(other)
+    }
+}
+
+impl PartialEq<[u8]> for Address {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.to_vec() == other
+    }
+}
+
+impl PartialEq<&[u8]> for Address {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<Option<Address>> for Address {
+    fn eq(&self, other: &Option<Address>) -> bool {
+        match other {
+            Some(o) => o == self,
+            None => self.is_anonymous(),
+        }
+    }
+}
+
+impl PartialEq<Address> for Option<Address> {
+    fn eq(&self, other: &Address) -> bool {
+        match self {
+            Some(s) => other == s,
+            None => other.is_anonymous(),
+        }
+    }
+}
+
+impl Debug for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let kind = match self.kind() {
+            Some(kind) => kind.to_string(),
+            // Only reachable with unchecked bytes.
+            None => "??".to_string(),
+        };
+        f.debug_tuple("Identity")
+            .field(&kind)
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
+/// A compact representation of an address for `tracing` fields; its kind and
+/// a truncated textual form.
+struct TracingField(Address);
+
+impl core::fmt::Display for TracingField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let address = self.0;
+        let s = address.to_string();
+        let kind = match address.kind() {
+            Some(AddressKind::Anonymous) => return f.write_str("anonymous"),
+            Some(kind) => kind.to_string(),
+            // Only reachable with unchecked bytes.
+            None => "??".to_string(),
+        };
+
+        write!(f, "{}:{}..{}", kind, &s[..8], &s[s.len() - 4..])
+    }
+}
+
+impl Default for Address {
+    fn default() -> Self {
+        Address::anonymous()
+    }
+}
+
+impl core::fmt::Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_textual(f)
+    }
+}
+
+impl TryFrom<&[u8]> for Address {
+    type Error = ManyError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Any public key hash is valid, see [Address::public_key_unchecked].
+impl From<PublicKeyHash> for Address {
+    fn from(hash: PublicKeyHash) -> Self {
+        Self::public_key_unchecked(hash)
+    }
+}
+
+/// The public key form; a kind byte of 1 followed by the public key hash.
+impl TryFrom<&[u8; 1 + SHA_OUTPUT_SIZE]> for Address {
+    type Error = ManyError;
+
+    fn try_from(bytes: &[u8; 1 + SHA_OUTPUT_SIZE]) -> Result<Self, Self::Error> {
+        match bytes[0] {
+            1 => Self::from_bytes(bytes),
+            0 => Err(ManyError::invalid_identity()),
+            x => Err(ManyError::invalid_identity_kind(x.to_string())),
+        }
+    }
+}
+
+/// The subresource form, or the anonymous and public key forms padded with
+/// zeroes (as returned by [Address::to_byte_array]).
+impl TryFrom<&[u8; MAX_IDENTITY_BYTE_LEN]> for Address {
+    type Error = ManyError;
+
+    fn try_from(bytes: &[u8; MAX_IDENTITY_BYTE_LEN]) -> Result<Self, Self::Error> {
+        match bytes[0] {
+            0 if bytes[1..].iter().all(|b| *b == 0) => Ok(Self::anonymous()),
+            1 if bytes[1 + SHA_OUTPUT_SIZE..].iter().all(|b| *b == 0) => {
+                Self::from_bytes(&bytes[..=SHA_OUTPUT_SIZE])
+            }
+            0 | 1 => Err(ManyError::invalid_identity()),
+            0x80..=0xFF => Self::from_bytes(bytes),
+            x => Err(ManyError::invalid_identity_kind(x.to_string())),
+        }
+    }
+}
+
+/// See [Address::from_cose_key_owned].
+#[cfg(feature = "coset")]
+impl TryFrom<coset::CoseKey> for Address {
+    type Error = ManyError;
+
+    fn try_from(key: coset::CoseKey) -> Result<Self, Self::Error> {
+        Self::from_cose_key_owned(key)
+    }
+}
+
+/// An address equals a key if it [matches](Address::matches_key) it, i.e. the
+/// key is the address' (ignoring subresource IDs). Anonymous never equals a
+/// key.
+#[cfg(feature = "coset")]
+impl PartialEq<coset::CoseKey> for Address {
+    fn eq(&self, other: &coset::CoseKey) -> bool {
+        self.matches_key(Some(other))
+    }
+}
+
+impl TryFrom<String> for Address {
+    type Error = ManyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        InnerAddress::try_from(value).map(Self)
+    }
+}
+
+impl FromStr for Address {
+    type Err = ManyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InnerAddress::from_str(s).map(Self)
+    }
+}
+
+impl AsRef<[u8; MAX_IDENTITY_BYTE_LEN]> for Address {
+    fn as_ref(&self) -> &[u8; MAX_IDENTITY_BYTE_LEN] {
+        &self.0.bytes
+    }
+}
+
+/// Returns a seed for a verifiable ordering of a set of addresses, e.g. a
+/// round-robin between participants. The seed is a SHA3-256 hash of the
+/// sorted and deduplicated addresses, so every party computes the same seed
+/// whatever order they received the addresses in.
+pub fn address_set_seed(ids: &[Address]) -> [u8; 32] {
+    use sha3::{Digest, Sha3_256};
+
+    let mut ids = ids.to_vec();
+    ids.sort();
+    ids.dedup();
+
+    // The kind byte gives the length of each address, so the concatenation
+    // is unambiguous.
+    let mut hasher = Sha3_256::new();
+    for id in ids {
+        hasher.update(id.to_vec());
+    }
+    hasher.finalize().into()
+}
+
+/// Writes the textual form of each address into `w`, separated by `sep`,
+/// without allocating a string per address.
+#[cfg(feature = "std")]
+pub fn render_addresses<W: std::io::Write>(
+    ids: &[Address],
+    sep: &str,
+    w: &mut W,
+) -> std::io::Result<()> {
+    /// Forwards the textual form to the writer, keeping its error.
+    struct Adapter<'a, W> {
+        inner: &'a mut W,
+        error: Option<std::io::Error>,
+    }
+
+    impl<W: std::io::Write> core::fmt::Write for Adapter<'_, W> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                core::fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        inner: w,
+        error: None,
+    };
+    let result = ids.iter().enumerate().try_for_each(|(i, id)| {
+        if i > 0 {
+            core::fmt::Write::write_str(&mut adapter, sep)?;
+        }
+        id.write_textual(&mut adapter)
+    });
+    match (result, adapter.error) {
+        (Ok(()), _) => Ok(()),
+        (Err(_), Some(e)) => Err(e),
+        (Err(e), None) => Err(std::io::Error::new(std::io::ErrorKind::Other, e)),
+    }
+}
+
+/// A curated list of addresses for downstream crates to seed fuzzers and
+/// property tests with; anonymous, public keys with edge case hashes, and
+/// subresources at the boundaries of the subresource ID encoding.
+#[cfg(feature = "corpus")]
+pub fn address_corpus() -> Vec<Address> {
+    let mut one_bit = [0; SHA_OUTPUT_SIZE];
+    one_bit[SHA_OUTPUT_SIZE - 1] = 1;
+    let mut counting = [0; SHA_OUTPUT_SIZE];
+    for (i, b) in counting.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let keys = [
+        [0; SHA_OUTPUT_SIZE],
+        one_bit,
+        counting,
+        [0xFF; SHA_OUTPUT_SIZE],
+    ];
+
+    let mut corpus = vec![Address::anonymous()];
+    for hash in keys {
+        let key = Address::public_key_unchecked(hash);
+        corpus.push(key);
+        for id in [
+            0,
+            1,
+            0xFF,
+            0x0100_0000,
+            0x00FF_FFFF,
+            *GUARDIAN_SUBRESOURCE_IDS.start(),
+            MAX_SUBRESOURCE_ID,
+        ] {
+            corpus.push(key.with_subresource_id_unchecked(SubresourceId(id)));
+        }
+    }
+    corpus
+}
+
+#[derive(Copy, Clone, Eq, Debug)]
+#[non_exhaustive]
+#[must_use]
+struct InnerAddress {
+    bytes: [u8; MAX_IDENTITY_BYTE_LEN],
+}
+
+// Identity needs to be bound to 32 bytes maximum.
+static_assertions::assert_eq_size!([u8; MAX_IDENTITY_BYTE_LEN], InnerAddress);
+static_assertions::const_assert_eq!(InnerAddress::anonymous().to_byte_array()[0], 0);
+static_assertions::const_assert_eq!(SUBRESOURCE_BYTE_LEN, MAX_IDENTITY_BYTE_LEN);
+
+/// Compares bytes in constant time, so checking a commitment or an address
+/// does not leak where the bytes differ.
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
+impl PartialEq for InnerAddress {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.bytes[0], &other.bytes[0]) {
+            // Anonymous
+            (0, 0) => true,
+
+            // Public Key
+            (1, 1) => ct_eq(
+                &self.bytes[1..=SHA_OUTPUT_SIZE],
+                &other.bytes[1..=SHA_OUTPUT_SIZE],
+            ),
+
+            // Subresource
+            (0x80..=0xFF, 0x80..=0xFF) => ct_eq(&self.bytes, &other.bytes),
+
+            // Reserved kinds cannot be decoded, but can be constructed
+            // unchecked. Compare all their bytes so equality stays reflexive.
+            (2..=0x7F, 2..=0x7F) => ct_eq(&self.bytes, &other.bytes),
+
+            // Anything else if by default inequal.
+            (_, _) => false,
+        }
+    }
+}
+
+// Hashes exactly the bytes `eq` compares, so equal addresses have the same
+// hash.
+impl core::hash::Hash for InnerAddress {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        match self.bytes[0] {
+            0 => state.write_u8(0),
+            1 => self.bytes[..=SHA_OUTPUT_SIZE].hash(state),
+            _ => self.bytes.hash(state),
+        }
+    }
+}
+
+impl PartialOrd for InnerAddress {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InnerAddress {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Addresses are ordered by kind, then public key hash, then subresource
+        // ID. Comparing the raw bytes would sort subresources by the high bits
+        // of their ID first, which are stored in the first byte.
+        self.order_key().cmp(&other.order_key())
+    }
+}
+
+impl Default for InnerAddress {
+    fn default() -> Self {
+        InnerAddress::anonymous()
+    }
+}
+
+impl InnerAddress {
+    pub const fn anonymous() -> Self {
+        Self {
+            bytes: [0; MAX_IDENTITY_BYTE_LEN],
+        }
+    }
+
+    pub const fn public_key(hash: [u8; SHA_OUTPUT_SIZE]) -> Self {
+        let mut bytes = [0; MAX_IDENTITY_BYTE_LEN];
+        bytes[0] = 1;
+        let mut len = SHA_OUTPUT_SIZE;
+        while len > 0 {
+            len -= 1;
+            bytes[1 + len] = hash[len];
+        }
+        Self { bytes }
+    }
+
+    pub(crate) const fn subresource_unchecked(
         hash: [u8; SHA_OUTPUT_SIZE],
         id: SubresourceId,
     ) -> Self {
         let id = id.0;
+        debug_assert!(id <= MAX_SUBRESOURCE_ID);
 
         // Get a public key and add the resource id.
         let mut bytes = Self::public_key(hash).bytes;
@@ -342,283 +1523,2218 @@ impl InnerAddress {
         Self { bytes }
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ManyError> {
-        let bytes = bytes;
-        if bytes.is_empty() {
-            return Err(ManyError::invalid_identity());
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ManyError> {
+        match (bytes.first(), bytes.len()) {
+            (Some(0), ANONYMOUS_BYTE_LEN) => Ok(Self::anonymous()),
+            (Some(1), PUBLIC_KEY_BYTE_LEN) => {
+                let mut hash = [0; SHA_OUTPUT_SIZE];
+                hash.copy_from_slice(&bytes[1..PUBLIC_KEY_BYTE_LEN]);
+                Ok(Self::public_key(hash))
+            }
+            (Some(hi @ 0x80..=0xFF), SUBRESOURCE_BYTE_LEN) => {
+                let mut hash = [0; SHA_OUTPUT_SIZE];
+                let mut subid = [0; 4];
+                hash.copy_from_slice(&bytes[1..PUBLIC_KEY_BYTE_LEN]);
+                subid[0] = hi & 0x7F;
+                subid[1..].copy_from_slice(&bytes[PUBLIC_KEY_BYTE_LEN..]);
+                Ok(Self::subresource_unchecked(
+                    hash,
+                    SubresourceId(u32::from_be_bytes(subid)),
+                ))
+            }
+            // A known kind with the wrong length.
+            (None, _) | (Some(0 | 1 | 0x80..=0xFF), _) => Err(ManyError::invalid_identity()),
+            (Some(x), _) => Err(ManyError::invalid_identity_kind(x.to_string())),
+        }
+    }
+
+    pub fn from_str(value: &str) -> Result<Self, ManyError> {
+        if !value.starts_with('m') {
+            return Err(ManyError::invalid_identity_prefix(
+                value.chars().next().unwrap_or_default().to_string(),
+            ));
+        }
+
+        // Anonymous is special cased (including its legacy form). Only exact
+        // matches are anonymous.
+        if value == "maa" || value == "maaaa" {
+            return Ok(Self::anonymous());
+        }
+
+        // Anything shorter than anonymous cannot have a body and a checksum.
+        if value.len() <= 3 {
+            return Err(ManyError::invalid_identity());
+        }
+
+        let result = Self::from_str_body(value)?;
+        if result.to_string() != value {
+            Err(ManyError::invalid_identity())
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub fn from_str_checked(value: &str) -> Result<Self, ManyError> {
+        if !value.starts_with('m') {
+            return Err(ManyError::invalid_identity_prefix(
+                value.chars().next().unwrap_or_default().to_string(),
+            ));
+        }
+        if value == "maa" || value == "maaaa" {
+            return Ok(Self::anonymous());
+        }
+        if value.len() <= 3 || !value.is_ascii() {
+            return Err(ManyError::invalid_identity());
+        }
+
+        let result = Self::from_str_body(value)?;
+        let (body, checksum) = value.split_at(value.len() - 2);
+        let textual = result.to_string();
+        if result.is_anonymous() || !textual.starts_with(body) || textual.len() != value.len() {
+            return Err(ManyError::invalid_identity());
+        }
+
+        let mut chars = checksum.chars();
+        if result.checksum() != [chars.next().unwrap(), chars.next().unwrap()] {
+            return Err(ManyError::invalid_identity_checksum());
+        }
+        Ok(result)
+    }
+
+    /// Decodes the body of a textual identity (without its prefix and
+    /// checksum), without validating the checksum.
+    fn from_str_body(value: &str) -> Result<Self, ManyError> {
+        let data = value
+            .len()
+            .checked_sub(2)
+            .and_then(|end| value.get(1..end))
+            .ok_or_else(ManyError::invalid_identity)?;
+        Self::from_base32(data)
+    }
+
+    /// Decodes the base32 bytes of an identity without allocating.
+    fn from_base32(data: &str) -> Result<Self, ManyError> {
+        // One byte longer than any identity, so longer data still fails on
+        // its length.
+        let mut bytes = [0; MAX_IDENTITY_BYTE_LEN + 1];
+        let mut len = 0;
+
+        // Unpadded RFC4648 base32 in either case, 5 bits at a time.
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        for c in data.bytes() {
+            let value = match c.to_ascii_lowercase() {
+                c @ b'a'..=b'z' => c - b'a',
+                c @ b'2'..=b'7' => c - b'2' + 26,
+                _ => return Err(ManyError::invalid_identity()),
+            };
+            buffer = (buffer << 5) | value as u16;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                if let Some(byte) = bytes.get_mut(len) {
+                    *byte = (buffer >> bits) as u8;
+                    len += 1;
+                }
+            }
+        }
+        Self::try_from(&bytes[..len])
+    }
+
+    pub const fn to_byte_array(self) -> [u8; MAX_IDENTITY_BYTE_LEN] {
+        self.bytes
+    }
+
+    #[rustfmt::skip]
+    pub fn to_vec(self) -> Vec<u8> {
+        // This makes sure we actually have a Vec<u8> that's smaller than 32 bytes if
+        // it can be.
+        match self.bytes[0] {
+            0 => vec![0],
+            1 => {
+                let pk = &self.bytes[1..=SHA_OUTPUT_SIZE];
+                vec![
+                    1,
+                    pk[ 0], pk[ 1], pk[ 2], pk[ 3], pk[ 4], pk[ 5], pk[ 6], pk[ 7],
+                    pk[ 8], pk[ 9], pk[10], pk[11], pk[12], pk[13], pk[14], pk[15],
+                    pk[16], pk[17], pk[18], pk[19], pk[20], pk[21], pk[22], pk[23],
+                    pk[24], pk[25], pk[26], pk[27],
+                ]
+            }
+            0x80..=0xFF => {
+                self.bytes.to_vec()
+            }
+            // Reserved kinds keep all their bytes, which every decoder
+            // rejects with an invalid kind error.
+            _ => {
+                self.bytes.to_vec()
+            }
+        }
+    }
+
+    pub const fn is_anonymous(&self) -> bool {
+        self.bytes[0] == 0
+    }
+    pub const fn is_public_key(&self) -> bool {
+        self.bytes[0] == 1
+    }
+    pub const fn is_subresource(&self) -> bool {
+        matches!(self.bytes[0], 0x80..=0xFF)
+    }
+    pub const fn is_illegal(&self) -> bool {
+        matches!(self.bytes[0], 2..=0x7F)
+    }
+
+    /// Whether the bytes are in canonical form; a known kind byte and only
+    /// zeroes after the significant bytes of that kind.
+    pub const fn is_canonical(&self) -> bool {
+        let mut i = match self.bytes[0] {
+            0 => 1,
+            1 => 1 + SHA_OUTPUT_SIZE,
+            0x80..=0xFF => return true,
+            _ => return false,
+        };
+        while i < MAX_IDENTITY_BYTE_LEN {
+            if self.bytes[i] != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    pub const fn subresource_id(&self) -> Option<u32> {
+        match self.bytes[0] {
+            x @ 0x80..=0xFF => {
+                let high = ((x & 0x7F) as u32) << 24;
+                let mut low = (self.bytes[SHA_OUTPUT_SIZE + 1] as u32) << 16;
+                low += (self.bytes[SHA_OUTPUT_SIZE + 2] as u32) << 8;
+                low += self.bytes[SHA_OUTPUT_SIZE + 3] as u32;
+                Some(high + low)
+            }
+            _ => None,
+        }
+    }
+
+    fn order_key(&self) -> [u8; ORDER_PRESERVING_KEY_LEN] {
+        let mut key = [0; ORDER_PRESERVING_KEY_LEN];
+        key[0] = match self.bytes[0] {
+            0 => 0,
+            1 => 1,
+            0x80..=0xFF => 2,
+            _ => 3,
+        };
+        if key[0] == 3 {
+            // Reserved kinds are equal only if all their bytes are, so they
+            // are ordered by all their bytes.
+            key[1..].copy_from_slice(&self.bytes);
+            return key;
+        }
+        if let Some(hash) = self.hash() {
+            key[1..=SHA_OUTPUT_SIZE].copy_from_slice(&hash);
+        }
+        if let Some(subid) = self.subresource_id() {
+            key[SHA_OUTPUT_SIZE + 1..].copy_from_slice(&subid.to_be_bytes());
+        }
+        key
+    }
+
+    pub const fn hash(&self) -> Option<[u8; SHA_OUTPUT_SIZE]> {
+        match self.bytes[0] {
+            1 | 0x80..=0xFF => {
+                let mut hash = [0; SHA_OUTPUT_SIZE];
+                let mut len = SHA_OUTPUT_SIZE;
+                while len > 0 {
+                    len -= 1;
+                    hash[len] = self.bytes[1 + len];
+                }
+                Some(hash)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for InnerAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_textual(f)
+    }
+}
+
+impl InnerAddress {
+    /// The number of significant bytes, i.e. the length of [Self::to_vec].
+    const fn significant_len(&self) -> usize {
+        match self.bytes[0] {
+            0 => ANONYMOUS_BYTE_LEN,
+            1 => PUBLIC_KEY_BYTE_LEN,
+            _ => MAX_IDENTITY_BYTE_LEN,
+        }
+    }
+
+    /// Writes the textual form without allocating.
+    fn write_textual<W: core::fmt::Write>(&self, w: &mut W) -> core::fmt::Result {
+        if self.is_anonymous() {
+            // Special case this.
+            return w.write_str("maa");
+        }
+
+        let data = &self.bytes[..self.significant_len()];
+        w.write_char('m')?;
+
+        // Unpadded RFC4648 base32, 5 bits at a time.
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        for byte in data {
+            buffer = (buffer << 8) | *byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                w.write_char(BASE32_ALPHABET[((buffer >> bits) & 0x1F) as usize] as char)?;
+            }
+        }
+        if bits > 0 {
+            w.write_char(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize] as char)?;
+        }
+
+        let [a, b] = self.checksum();
+        w.write_char(a)?;
+        w.write_char(b)
+    }
+
+    /// The CRC16 of the significant bytes.
+    fn crc16(&self) -> u16 {
+        let mut crc = crc_any::CRCu16::crc16();
+        crc.digest(&self.bytes[..self.significant_len()]);
+        crc.get_crc()
+    }
+
+    /// The checksum is the first 2 base32 characters of the CRC16 of the
+    /// significant bytes.
+    fn checksum(&self) -> [char; 2] {
+        let crc = self.crc16();
+        [
+            BASE32_ALPHABET[(crc >> 11) as usize] as char,
+            BASE32_ALPHABET[((crc >> 6) & 0x1F) as usize] as char,
+        ]
+    }
+}
+
+impl TryFrom<String> for InnerAddress {
+    type Error = ManyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        InnerAddress::from_str(value.as_str())
+    }
+}
+
+impl TryFrom<&[u8]> for InnerAddress {
+    type Error = ManyError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::testing::identity;
+    use crate::Address;
+    use serde_test::{assert_tokens, Configure, Token};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_read_anonymous() {
+        let a = Address::anonymous();
+        let a_str = a.to_string();
+        let a2 = Address::from_str(&a_str).unwrap();
+
+        assert_eq!(a, a2);
+    }
+
+    #[test]
+    fn can_read_anonymous_short() {
+        assert_eq!(Address::from_str("maa"), Ok(Address::anonymous()));
+    }
+
+    #[test]
+    fn byte_array_conversion() {
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = identity(2);
+
+        assert_ne!(a.to_string(), b.to_string());
+        assert_ne!(b.to_string(), c.to_string());
+        assert_ne!(a.to_vec(), b.to_vec());
+        assert_ne!(b.to_vec(), c.to_vec());
+
+        assert_eq!(Address::from_str(&a.to_string()), Ok(a));
+        assert_eq!(Address::from_str(&b.to_string()), Ok(b));
+        assert_eq!(Address::from_str(&c.to_string()), Ok(c));
+    }
+
+    #[test]
+    fn textual_format_1() {
+        let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys").unwrap();
+        let b = Address::from_bytes(
+            &hex::decode("01c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn from_public_key_hash() {
+        let hash: super::PublicKeyHash =
+            hex::decode("c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let a = Address::from(hash);
+
+        assert_eq!(a, Address::public_key_unchecked(hash));
+        assert_eq!(
+            a.to_string(),
+            "mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys"
+        );
+    }
+
+    #[test]
+    fn textual_format_2() {
+        let a =
+            Address::from_str("mqbfbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wiaaaaqnz").unwrap();
+        let b = Address::from_bytes(
+            &hex::decode("804a101d521d810211a0c6346ba89bd1cc1f821c03b969ff9d5c8b2f59000001")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn subresource_1() {
+        let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys")
+            .unwrap()
+            .with_subresource_id(1)
+            .unwrap();
+        let b = Address::from_bytes(
+            &hex::decode("80c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22000001")
+                .unwrap(),
+        )
+        .unwrap();
+        let c = Address::from_bytes(
+            &hex::decode("80c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22000002")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(b.with_subresource_id(2).unwrap(), c);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn subresource_id_fuzzy(subid: u32) {
+            let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys")
+                .unwrap()
+                .with_subresource_id(subid);
+
+            if let Ok(id) = a {
+                let b = Address::from_str(&id.to_string());
+                assert_eq!(a, b);
+            } else {
+                assert_eq!(subid.leading_zeros(), 0);
+            }
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn from_bytes_lengths(
+            kind in proptest::prop_oneof![
+                proptest::strategy::Just(0u8),
+                proptest::strategy::Just(1u8),
+                0x80u8..=0xFF,
+            ],
+            rest in proptest::collection::vec(proptest::num::u8::ANY, 0..64),
+        ) {
+            let mut bytes = vec![kind];
+            bytes.extend(rest);
+            let expected = match kind {
+                0 => super::ANONYMOUS_BYTE_LEN,
+                1 => super::PUBLIC_KEY_BYTE_LEN,
+                _ => super::SUBRESOURCE_BYTE_LEN,
+            };
+            proptest::prop_assert_eq!(Address::from_bytes(&bytes).is_ok(), bytes.len() == expected);
+        }
+    }
+
+    #[test]
+    fn serde_anonymous() {
+        let id = Address::anonymous();
+        assert_tokens(&id.readable(), &[Token::String("maa")]);
+        assert_tokens(&id.compact(), &[Token::Bytes(&[0])]);
+    }
+
+    #[test]
+    fn padded_string() {
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = b.with_subresource_id(1).unwrap();
+
+        for id in [a, b, c] {
+            let padded = id.to_padded_string(60).unwrap();
+            assert_eq!(padded.len(), 60);
+            assert!(padded.starts_with(&id.to_string()));
+            assert!(padded.ends_with(super::TEXTUAL_PADDING_CHAR));
+            assert_eq!(Address::from_padded_string(&padded), Ok(id));
+        }
+
+        // Exact width needs no padding.
+        let b_str = b.to_string();
+        assert_eq!(b.to_padded_string(b_str.len()), Ok(b_str));
+    }
+
+    #[test]
+    fn subresource_suffix() {
+        let base = identity(1);
+        let s = base.to_string();
+
+        assert_eq!(
+            Address::from_str_with_subresource_suffix(&format!("{}@1234", s)),
+            base.with_subresource_id(1234u32)
+        );
+        assert_eq!(
+            Address::from_str_with_subresource_suffix(&format!("{}@0x4d2", s)),
+            base.with_subresource_id(1234u32)
+        );
+        assert_eq!(Address::from_str_with_subresource_suffix(&s), Ok(base));
+
+        let sub = base.with_subresource_id(1234u32).unwrap();
+        assert_eq!(sub.to_str_with_subresource_suffix(), format!("{}@1234", s));
+        assert_eq!(base.to_str_with_subresource_suffix(), s);
+        assert_eq!(
+            Address::from_str_with_subresource_suffix(&sub.to_str_with_subresource_suffix()),
+            Ok(sub)
+        );
+    }
+
+    #[test]
+    fn subresource_suffix_invalid() {
+        let s = identity(1).to_string();
+        for suffix in ["0x80000000", "2147483648", "-1", "", "0x", "abc"] {
+            assert_eq!(
+                Address::from_str_with_subresource_suffix(&format!("{}@{}", s, suffix))
+                    .unwrap_err()
+                    .code(),
+                many_error::ManyError::invalid_identity_subid().code()
+            );
+        }
+
+        for suffix in ["0", "1"] {
+            assert_eq!(
+                Address::from_str_with_subresource_suffix(&format!("maa@{}", suffix))
+                    .unwrap_err()
+                    .code(),
+                many_error::ManyError::invalid_identity_subid().code()
+            );
+        }
+    }
+
+    #[test]
+    fn epoch_alias() {
+        let id = identity(1);
+        assert_eq!(id.epoch_alias(1), id.epoch_alias(1));
+        assert_ne!(id.epoch_alias(1), id.epoch_alias(2));
+        assert_ne!(id.epoch_alias(1), identity(2).epoch_alias(1));
+        assert_ne!(
+            id.epoch_alias(1),
+            id.with_subresource_id(1).unwrap().epoch_alias(1)
+        );
+    }
+
+    #[test]
+    fn bloom_hashes() {
+        let id = identity(1);
+        let parsed = Address::from_str(&id.to_string()).unwrap();
+        assert_eq!(id.bloom_hashes(7), parsed.bloom_hashes(7));
+        assert_eq!(id.bloom_hashes(7).len(), 7);
+        assert!(id.bloom_hashes(0).is_empty());
+
+        // Fewer hashes are a prefix of more hashes.
+        assert_eq!(id.bloom_hashes(3), id.bloom_hashes(7)[..3]);
+
+        let hashes = id.bloom_hashes(7);
+        let unique: std::collections::BTreeSet<_> = hashes.iter().collect();
+        assert_eq!(unique.len(), 7);
+        assert_ne!(hashes, identity(2).bloom_hashes(7));
+        assert_ne!(hashes, id.with_subresource_id(1).unwrap().bloom_hashes(7));
+        assert_ne!(hashes, Address::anonymous().bloom_hashes(7));
+    }
+
+    #[test]
+    fn subresource_commitment() {
+        let blinding = [7u8; 32];
+        let id = identity(1).with_subresource_id(5).unwrap();
+        let commitment = id.subresource_commitment(&blinding);
+
+        assert!(id.verify_subresource_commitment(&commitment, &blinding));
+        assert!(!id.verify_subresource_commitment(&commitment, &[8u8; 32]));
+        assert!(!identity(1)
+            .with_subresource_id(6)
+            .unwrap()
+            .verify_subresource_commitment(&commitment, &blinding));
+        assert!(!identity(2)
+            .with_subresource_id(5)
+            .unwrap()
+            .verify_subresource_commitment(&commitment, &blinding));
+
+        // Public keys commit as their default account.
+        assert_eq!(
+            identity(1).subresource_commitment(&blinding),
+            identity(1)
+                .default_account()
+                .subresource_commitment(&blinding)
+        );
+    }
+
+    #[test]
+    fn diagnose_textual() {
+        use super::TextualDiagnosis;
+
+        let id = identity(1).with_subresource_id(2).unwrap();
+        let s = id.to_string();
+        assert_eq!(Address::diagnose_textual(&s), TextualDiagnosis::Valid(id));
+        assert_eq!(
+            Address::diagnose_textual("maa"),
+            TextualDiagnosis::Valid(Address::anonymous())
+        );
+
+        let (body, checksum) = s.split_at(s.len() - 2);
+        let bad_checksum = if checksum == "aa" { "bb" } else { "aa" };
+        assert_eq!(
+            Address::diagnose_textual(&format!("{}{}", body, bad_checksum)),
+            TextualDiagnosis::BadChecksumButBodyValid(id)
+        );
+
+        for bad in [
+            "",
+            "m",
+            "x",
+            "maab",
+            "m!!!!!!!!!!!aa",
+            &s[..20],
+            &s.to_uppercase(),
+        ] {
+            assert_eq!(Address::diagnose_textual(bad), TextualDiagnosis::BadBody);
+        }
+    }
+
+    #[test]
+    fn eq_bytes() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let bytes = id.to_vec();
+            assert!(id == bytes[..]);
+            assert!(id == bytes.as_slice());
+            assert!(id != identity(3).to_vec().as_slice());
+        }
+
+        // Bytes that are not canonical do not match.
+        let mut padded = identity(1).to_vec();
+        padded.extend_from_slice(&[0; 3]);
+        assert!(identity(1) != padded.as_slice());
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn otel_value() {
+        let id = identity(1).with_subresource_id(2).unwrap();
+        assert_eq!(id.otel_value(), opentelemetry::Value::from(id.to_string()));
+
+        let attribute = id.otel_attribute();
+        assert_eq!(attribute.key.as_str(), super::OTEL_ATTRIBUTE_KEY);
+        assert_eq!(attribute.value.as_str(), id.to_string());
+    }
+
+    #[test]
+    fn find_vanity_subresource() {
+        let id = identity(1);
+        let target = id.with_subresource_id(5u32 << 24).unwrap().to_string();
+        let prefix = &target[1..3];
+
+        let (subid, found) = id.find_vanity_subresource(prefix, 128).unwrap();
+        assert!(found.to_string()[1..].starts_with(prefix));
+        assert_eq!(found.subresource_id(), Some(subid));
+        assert!(found.matches(&id));
+
+        // The search is deterministic.
+        assert_eq!(
+            id.find_vanity_subresource(prefix, 128),
+            Some((subid, found))
+        );
+
+        // Not found within the budget.
+        assert_eq!(id.find_vanity_subresource("aaaa", 128), None);
+        assert_eq!(id.find_vanity_subresource(prefix, 0), None);
+        assert_eq!(Address::anonymous().find_vanity_subresource("", 1), None);
+    }
+
+    #[test]
+    fn from_env() {
+        let id = identity(1).with_subresource_id(2).unwrap();
+        std::env::set_var("MANY_IDENTITY_TEST_FROM_ENV", format!(" {}\n", id));
+        assert_eq!(Address::from_env("MANY_IDENTITY_TEST_FROM_ENV"), Ok(id));
+
+        std::env::set_var(
+            "MANY_IDENTITY_TEST_FROM_ENV_UPPERCASE",
+            id.to_string().to_uppercase(),
+        );
+        assert_eq!(
+            Address::from_env("MANY_IDENTITY_TEST_FROM_ENV_UPPERCASE"),
+            Ok(id)
+        );
+    }
+
+    #[test]
+    fn from_env_error() {
+        let err = Address::from_env("MANY_IDENTITY_TEST_FROM_ENV_MISSING").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("MANY_IDENTITY_TEST_FROM_ENV_MISSING"));
+
+        std::env::set_var("MANY_IDENTITY_TEST_FROM_ENV_INVALID", "mbbbbbb");
+        let err = Address::from_env("MANY_IDENTITY_TEST_FROM_ENV_INVALID").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("MANY_IDENTITY_TEST_FROM_ENV_INVALID"));
+    }
+
+    #[test]
+    fn proto_bytes() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let bytes = id.to_proto_bytes();
+            assert_eq!(bytes, id.to_vec());
+            assert_eq!(Address::from_proto_bytes(&bytes), Ok(id));
+        }
+    }
+
+    #[test]
+    fn tagged_binary() {
+        for (id, header) in [
+            (Address::anonymous(), 0x00),
+            (identity(1), 0x01),
+            (identity(1).with_subresource_id(2).unwrap(), 0x02),
+        ] {
+            let bytes = id.to_tagged_binary().unwrap();
+            assert_eq!(bytes[0], header);
+            assert_eq!(&bytes[1..], id.to_vec().as_slice());
+            assert_eq!(Address::from_tagged_binary(&bytes), Ok(id));
+        }
+
+        // Unknown version.
+        let mut bytes = identity(1).to_tagged_binary().unwrap();
+        bytes[0] = 0x11;
+        assert!(Address::from_tagged_binary(&bytes).is_err());
+
+        // The kind does not match the payload.
+        bytes[0] = 0x02;
+        assert_eq!(
+            Address::from_tagged_binary(&bytes).unwrap_err().code(),
+            many_error::ManyErrorCode::InvalidIdentityKind
+        );
+
+        assert!(Address::from_tagged_binary(&[]).is_err());
+        assert!(Address::from_tagged_binary(&[0x01]).is_err());
+
+        // Reserved kinds cannot be encoded.
+        let mut bytes = identity(1).to_byte_array();
+        bytes[0] = 0x42;
+        let reserved = super::Address(super::InnerAddress { bytes });
+        assert_eq!(
+            reserved.to_tagged_binary().unwrap_err().code(),
+            many_error::ManyErrorCode::InvalidIdentityKind
+        );
+    }
+
+    #[test]
+    fn from_bytes_with() {
+        use many_error::ManyErrorCode;
+
+        let legacy =
+            |kind: u8, rest: &[u8]| (kind == 2 && rest.is_empty()).then(Address::anonymous);
+        assert_eq!(
+            Address::from_bytes_with(&[2], legacy),
+            Ok(Address::anonymous())
+        );
+        assert_eq!(
+            Address::from_bytes_with(&[2, 0], legacy)
+                .unwrap_err()
+                .code(),
+            ManyErrorCode::InvalidIdentityKind
+        );
+        assert_eq!(
+            Address::from_bytes_with(&[0x7F], |_, _| None)
+                .unwrap_err()
+                .code(),
+            ManyErrorCode::InvalidIdentityKind
+        );
+
+        // Known kinds do not call the callback.
+        let id = identity(1).with_subresource_id(2).unwrap();
+        for id in [Address::anonymous(), identity(1), id] {
+            let decoded = Address::from_bytes_with(&id.to_vec(), |_, _| unreachable!());
+            assert_eq!(decoded, Ok(id));
+        }
+        assert!(Address::from_bytes_with(&[], |_, _| unreachable!()).is_err());
+    }
+
+    #[test]
+    fn proto_bytes_malformed() {
+        let mut padded = identity(1).to_proto_bytes();
+        padded.push(0);
+        for bytes in [&[][..], &[0, 0], &[1, 2, 3], &[2], &padded] {
+            assert!(Address::from_proto_bytes(bytes).is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn avatar_colors() {
+        let is_color = |c: &str| {
+            c.len() == 7 && c.starts_with('#') && c[1..].chars().all(|c| c.is_ascii_hexdigit())
+        };
+
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let (a, b) = id.avatar_colors();
+            assert!(is_color(&a), "{}", a);
+            assert!(is_color(&b), "{}", b);
+
+            let copy = Address::from_str(&id.to_string()).unwrap();
+            assert_eq!(copy.avatar_colors(), (a, b));
+        }
+        assert_ne!(identity(1).avatar_colors(), identity(2).avatar_colors());
+    }
+
+    #[test]
+    fn group_by_key() {
+        let (a, b) = (identity(1), identity(2));
+        let a1 = a.with_subresource_id(1).unwrap();
+        let a2 = a.with_subresource_id(2).unwrap();
+        let b1 = b.with_subresource_id(1).unwrap();
+        let anon = Address::anonymous();
+
+        let groups = Address::group_by_key(&[a2, b1, anon, a, a1]);
+        assert_eq!(
+            groups,
+            BTreeMap::from([(a, vec![a2, a, a1]), (b, vec![b1]), (anon, vec![anon])])
+        );
+        assert!(Address::group_by_key(&[]).is_empty());
+    }
+
+    #[test]
+    fn try_from_fixed_29() {
+        use many_error::ManyErrorCode;
+
+        let id = identity(1);
+        let bytes: [u8; 29] = id.to_vec().try_into().unwrap();
+        assert_eq!(Address::try_from(&bytes), Ok(id));
+
+        for kind in [0u8, 2, 0x7F, 0x80] {
+            let mut bytes = bytes;
+            bytes[0] = kind;
+            assert!(Address::try_from(&bytes).is_err());
+        }
+        let mut bytes = bytes;
+        bytes[0] = 2;
+        assert_eq!(
+            Address::try_from(&bytes).unwrap_err().code(),
+            ManyErrorCode::InvalidIdentityKind
+        );
+    }
+
+    #[test]
+    fn try_from_fixed_32() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            assert_eq!(Address::try_from(&id.to_byte_array()), Ok(id));
+        }
+
+        // Padding must be zeroes.
+        let mut bytes = Address::anonymous().to_byte_array();
+        bytes[31] = 1;
+        assert!(Address::try_from(&bytes).is_err());
+        let mut bytes = identity(1).to_byte_array();
+        bytes[31] = 1;
+        assert!(Address::try_from(&bytes).is_err());
+
+        for kind in [2u8, 0x7F] {
+            let mut bytes = identity(1).with_subresource_id(2).unwrap().to_byte_array();
+            bytes[0] = kind;
+            assert!(Address::try_from(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn from_bytes_lenient() {
+        let padded_anonymous = [0u8; 3];
+        assert_eq!(
+            Address::from_bytes(&padded_anonymous),
+            Err(many_error::ManyError::invalid_identity())
+        );
+        assert_eq!(
+            Address::from_bytes_lenient(&padded_anonymous),
+            Ok(Address::anonymous())
+        );
+
+        // CBOR decoding is strict.
+        #[cfg(feature = "minicbor")]
+        {
+            let cbor = [0xd9, 0x27, 0x10, 0x43, 0, 0, 0];
+            assert!(minicbor::decode::<Address>(&cbor).is_err());
+        }
+
+        let mut padded = identity(1).to_vec();
+        padded.extend_from_slice(&[0, 0]);
+        assert!(Address::from_bytes(&padded).is_err());
+        assert_eq!(Address::from_bytes_lenient(&padded), Ok(identity(1)));
+
+        // Garbage is not padding, and a public key cannot be truncated.
+        assert!(Address::from_bytes_lenient(&[0, 1]).is_err());
+        padded.push(1);
+        assert!(Address::from_bytes_lenient(&padded).is_err());
+        assert!(Address::from_bytes_lenient(&identity(1).to_vec()[..20]).is_err());
+        assert!(Address::from_bytes_lenient(&[]).is_err());
+
+        let sub = identity(1).with_subresource_id(2).unwrap();
+        assert_eq!(Address::from_bytes_lenient(&sub.to_vec()), Ok(sub));
+
+        // Padding is bounded by the fixed 32 bytes form.
+        assert_eq!(
+            Address::from_bytes_lenient(&[0; 32]),
+            Ok(Address::anonymous())
+        );
+        assert_eq!(
+            Address::from_bytes_lenient(&[0; 33]),
+            Err(many_error::ManyError::invalid_identity())
+        );
+        let mut padded = identity(1).to_vec();
+        padded.resize(4096, 0);
+        assert!(Address::from_bytes_lenient(&padded).is_err());
+    }
+
+    #[test]
+    fn from_fixed32() {
+        assert_eq!(Address::from_fixed32([0; 32]), Ok(Address::anonymous()));
+
+        let mut bytes = [0u8; 32];
+        bytes[..29].copy_from_slice(&identity(1).to_vec());
+        assert_eq!(Address::from_fixed32(bytes), Ok(identity(1)));
+        assert!(Address::from_bytes(&bytes).is_err());
+
+        let sub = identity(1).with_subresource_id(2).unwrap();
+        assert_eq!(Address::from_fixed32(sub.to_byte_array()), Ok(sub));
+
+        bytes[31] = 1;
+        assert!(Address::from_fixed32(bytes).is_err());
+    }
+
+    #[test]
+    fn checksum_u16() {
+        for id in [identity(1), identity(2).with_subresource_id(3).unwrap()] {
+            let s = id.to_string();
+            let crc = id.checksum_u16();
+
+            let alphabet = base32::Alphabet::RFC4648 { padding: false };
+            let rendered = base32::encode(alphabet, &crc.to_be_bytes());
+            assert_eq!(&s[s.len() - 2..], rendered[..2].to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn repair_checksum() {
+        let id = identity(1).with_subresource_id(2).unwrap();
+        let s = id.to_string();
+        assert_eq!(Address::repair_checksum(&s), None);
+
+        let (body, checksum) = s.split_at(s.len() - 2);
+        let bad_checksum = if checksum == "aa" { "bb" } else { "aa" };
+        assert_eq!(
+            Address::repair_checksum(&format!("{}{}", body, bad_checksum)),
+            Some(s.clone())
+        );
+
+        assert_eq!(Address::repair_checksum(&format!("m1{}", &s[2..])), None);
+        assert_eq!(Address::repair_checksum(""), None);
+        assert_eq!(Address::repair_checksum("m"), None);
+    }
+
+    #[test]
+    fn from_str_checked() {
+        use many_error::ManyErrorCode;
+
+        for id in [
+            identity(1),
+            identity(2).with_subresource_id(3).unwrap(),
+            Address::anonymous(),
+        ] {
+            let s = id.to_string();
+            assert_eq!(Address::from_str_checked(&s).unwrap(), id);
+            assert_eq!(id.checksum().iter().collect::<String>(), &s[s.len() - 2..]);
+        }
+        assert!(Address::from_str_checked("maaaa").unwrap().is_anonymous());
+
+        // Corrupt only the checksum characters.
+        let s = identity(1).to_string();
+        let (body, checksum) = s.split_at(s.len() - 2);
+        let corrupted = if checksum == "aa" {
+            format!("{}bb", body)
+        } else {
+            format!("{}aa", body)
+        };
+        assert_eq!(
+            Address::from_str_checked(&corrupted).unwrap_err().code(),
+            ManyErrorCode::InvalidIdentityChecksum
+        );
+        assert_eq!(
+            Address::from_str(&corrupted).unwrap_err().code(),
+            ManyErrorCode::InvalidIdentity
+        );
+
+        // A corrupted body is not a checksum error.
+        let corrupted = format!("m1{}", &s[2..]);
+        assert_eq!(
+            Address::from_str_checked(&corrupted).unwrap_err().code(),
+            ManyErrorCode::InvalidIdentity
+        );
+        assert!(Address::from_str_checked("x").is_err());
+        assert!(Address::from_str_checked("m").is_err());
+        assert!(Address::from_str_checked("maab").is_err());
+    }
+
+    #[test]
+    fn from_str_normalized() {
+        let id = identity(1).with_subresource_id(2).unwrap();
+        let s = id.to_string();
+
+        assert_eq!(Address::from_str_normalized(&s), Ok((id, false)));
+        assert_eq!(
+            Address::from_str_normalized(" maa "),
+            Ok((Address::anonymous(), true))
+        );
+        assert_eq!(
+            Address::from_str_normalized(&s.to_uppercase()),
+            Ok((id, true))
+        );
+
+        let mixed: String = s
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if i % 2 == 0 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        assert_eq!(
+            Address::from_str_normalized(&format!("\t{}\n", mixed)),
+            Ok((id, true))
+        );
+        assert!(Address::from_str_normalized(&s[1..]).is_err());
+    }
+
+    #[test]
+    fn as_tracing_field() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        #[derive(Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Recorder {
+            fn record_debug(&mut self, _: &Field, value: &dyn std::fmt::Debug) {
+                self.0.lock().unwrap().push(format!("{:?}", value));
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                span.record(&mut Recorder(self.0.clone()));
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let id = identity(1).with_subresource_id(3).unwrap();
+        let s = id.to_string();
+        let k = identity(1).to_string();
+        let recorder = Recorder::default();
+        let fields = recorder.0.clone();
+        tracing::subscriber::with_default(recorder, || {
+            let _span = tracing::info_span!("test", id = id.as_tracing_field());
+            let _span = tracing::info_span!("test", id = identity(1).as_tracing_field());
+            let _span = tracing::info_span!("test", id = Address::anonymous().as_tracing_field());
+        });
+
+        let fields = fields.lock().unwrap();
+        assert_eq!(
+            *fields,
+            vec![
+                format!("subresource(3):{}..{}", &s[..8], &s[s.len() - 4..]),
+                format!("public-key:{}..{}", &k[..8], &k[k.len() - 4..]),
+                "anonymous".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_progress() {
+        use super::ParseProgress;
+
+        for id in [identity(1), identity(2).with_subresource_id(3).unwrap()] {
+            let s = id.to_string();
+            assert_eq!(Address::parse_progress(""), ParseProgress::Empty);
+            for i in 1..s.len() - 2 {
+                assert_eq!(
+                    Address::parse_progress(&s[..i]),
+                    ParseProgress::PrefixOk,
+                    "{}",
+                    i
+                );
+            }
+            assert_eq!(
+                Address::parse_progress(&s[..s.len() - 2]),
+                ParseProgress::BodyDecodes(id)
+            );
+            assert_eq!(
+                Address::parse_progress(&s[..s.len() - 1]),
+                ParseProgress::BodyDecodes(id)
+            );
+            assert_eq!(Address::parse_progress(&s), ParseProgress::ChecksumOk(id));
+        }
+        assert_eq!(
+            Address::parse_progress("maa"),
+            ParseProgress::ChecksumOk(Address::anonymous())
+        );
+    }
+
+    #[test]
+    fn parse_progress_invalid() {
+        use super::ParseProgress;
+
+        let s = identity(1).to_string();
+        let (body, checksum) = s.split_at(s.len() - 2);
+        let bad_checksum = if checksum.starts_with('a') {
+            "bb"
+        } else {
+            "aa"
+        };
+
+        for bad in [
+            "x".to_string(),
+            "m1".to_string(),
+            format!("{}{}", body, bad_checksum),
+            format!("{}{}", body, &bad_checksum[..1]),
+            format!("{}a", s.repeat(2)),
+        ] {
+            assert!(
+                matches!(Address::parse_progress(&bad), ParseProgress::Invalid(_)),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn subresource_parts() {
+        let id = identity(1);
+        for (region, seq) in [(0, 0), (0, 1), (1, 0), (0x7FFF, 0xFFFF), (123, 4567)] {
+            let sub = id.with_subresource_parts(region, seq).unwrap();
+            assert_eq!(sub.subresource_parts(), Some((region, seq)));
+            assert!(sub.matches(&id));
+        }
+
+        // The highest bit of the region does not fit.
+        assert_eq!(
+            id.with_subresource_parts(0x8001, 2),
+            id.with_subresource_parts(1, 2)
+        );
+        assert_eq!(
+            id.with_subresource_parts(1, 2).unwrap().subresource_id(),
+            Some(0x0001_0002)
+        );
+        assert_eq!(id.subresource_parts(), None);
+    }
+
+    #[test]
+    fn guardian_subresource() {
+        let id = identity(1);
+        let guardians: Vec<Address> = (0..=u8::MAX)
+            .map(|i| id.guardian_subresource(i).unwrap())
+            .collect();
+
+        for g in &guardians {
+            assert!(g.matches(&id));
+            assert!(super::GUARDIAN_SUBRESOURCE_IDS.contains(&g.subresource_id().unwrap()));
+        }
+        assert_eq!(guardians[0].subresource_id(), Some(0x7FFF_FF00));
+        assert_eq!(
+            guardians[255].subresource_id(),
+            Some(super::MAX_SUBRESOURCE_ID)
+        );
+
+        let unique: std::collections::BTreeSet<_> = guardians.iter().collect();
+        assert_eq!(unique.len(), 256);
+
+        let err = Address::anonymous().guardian_subresource(0).unwrap_err();
+        assert_eq!(err.code(), many_error::ManyErrorCode::InvalidIdentity);
+    }
+
+    #[test]
+    fn verification_code() {
+        for digits in [0, 1, 6, 18, 40] {
+            let code = identity(1).verification_code(digits);
+            assert_eq!(code.len(), digits);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+
+            let copy = Address::from_str(&identity(1).to_string()).unwrap();
+            assert_eq!(copy.verification_code(digits), code);
+        }
+        assert_ne!(
+            identity(1).verification_code(12),
+            identity(2).verification_code(12)
+        );
+        assert!(identity(1)
+            .verification_code(40)
+            .starts_with(&identity(1).verification_code(18)));
+    }
+
+    #[test]
+    fn nearby_subresource_ids() {
+        let key = identity(1);
+        let sub = |id: u32| key.with_subresource_id(id).unwrap();
+        let others = [
+            sub(100),
+            sub(98),
+            sub(103),
+            sub(104),
+            sub(5000),
+            key,
+            Address::anonymous(),
+            identity(2).with_subresource_id(101).unwrap(),
+        ];
+
+        assert_eq!(
+            sub(100).nearby_subresource_ids(&others, 3),
+            vec![(sub(98), 2), (sub(103), 3)]
+        );
+        assert_eq!(sub(100).nearby_subresource_ids(&others, 0), vec![]);
+        assert_eq!(key.nearby_subresource_ids(&others, 10), vec![]);
+        assert_eq!(
+            Address::anonymous().nearby_subresource_ids(&others, 10),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn subresource_id_fits() {
+        let id = identity(1);
+        let max = (1u32 << 20) - 1;
+
+        let sub = id.with_subresource_id_bounded(max, 20).unwrap();
+        assert_eq!(sub, id.with_subresource_id(max).unwrap());
+        assert!(sub.subresource_id_fits(20));
+        assert!(!sub.subresource_id_fits(19));
+        assert!(sub.subresource_id_fits(32));
+
+        assert_eq!(
+            id.with_subresource_id_bounded(max + 1, 20)
+                .unwrap_err()
+                .code(),
+            many_error::ManyErrorCode::InvalidIdentitySubResourceId
+        );
+        let sub = id.with_subresource_id(max + 1).unwrap();
+        assert!(!sub.subresource_id_fits(20));
+        assert!(sub.subresource_id_fits(21));
+
+        assert!(!id.subresource_id_fits(32));
+        assert!(!Address::anonymous().subresource_id_fits(32));
+    }
+
+    #[test]
+    fn subresource_id_checked() {
+        use many_error::ManyErrorCode;
+
+        let sub = identity(1).with_subresource_id(0x0102_0304).unwrap();
+        assert_eq!(sub.subresource_id_checked().unwrap(), 0x0102_0304);
+
+        // A reserved kind byte carrying a hash and subresource low bytes.
+        let mut bytes = sub.to_byte_array();
+        bytes[0] = 0x42;
+        let reserved = super::Address(super::InnerAddress { bytes });
+        assert_eq!(reserved.subresource_id(), None);
+        let err = reserved.subresource_id_checked().unwrap_err();
+        assert_eq!(err.code(), ManyErrorCode::InvalidIdentityKind);
+        assert_eq!(err.invalid_identity_kind_byte(), Some(0x42));
+
+        // A public key carrying subresource low bytes.
+        bytes[0] = 1;
+        let inconsistent = super::Address(super::InnerAddress { bytes });
+        assert_eq!(inconsistent.subresource_id(), None);
+        for id in [inconsistent, identity(1), Address::anonymous()] {
+            assert_eq!(
+                id.subresource_id_checked().unwrap_err().code(),
+                ManyErrorCode::InvalidIdentitySubResourceId
+            );
+        }
+    }
+
+    #[test]
+    fn is_valid_source() {
+        assert!(Address::anonymous().is_valid_source());
+        assert!(identity(1).is_valid_source());
+        assert!(identity(1)
+            .with_subresource_id(2)
+            .unwrap()
+            .is_valid_source());
+
+        for bytes in [
+            [2u8; 32],
+            [0x7F; 32],
+            {
+                let mut b = [0u8; 32];
+                b[31] = 1;
+                b
+            },
+            {
+                let mut b = identity(1).to_byte_array();
+                b[30] = 1;
+                b
+            },
+        ] {
+            let id = super::Address(super::InnerAddress { bytes });
+            assert!(!id.is_valid_source(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn validate_borrowed() {
+        let s = identity(1).with_subresource_id(2).unwrap().to_string();
+        let validated = Address::validate_borrowed(&s).unwrap();
+        assert_eq!(validated.as_ptr(), s.as_ptr());
+        assert_eq!(Address::validate_borrowed("maa"), Ok("maa"));
+
+        // Legacy anonymous is accepted by `from_str` but not canonical.
+        assert!(Address::validate_borrowed("maaaa").is_err());
+        assert!(Address::validate_borrowed(&s.to_uppercase()).is_err());
+        assert!(Address::validate_borrowed(&s[..s.len() - 1]).is_err());
+        assert!(Address::validate_borrowed("").is_err());
+    }
+
+    #[test]
+    fn padded_string_too_short() {
+        assert!(identity(1).to_padded_string(10).is_err());
+        assert!(Address::anonymous().to_padded_string(2).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "corpus")]
+    fn address_corpus() {
+        let corpus = super::address_corpus();
+        assert_eq!(corpus.len(), 33);
+        assert!(corpus.contains(&Address::anonymous()));
+
+        for id in corpus {
+            assert_eq!(Address::from_str(&id.to_string()), Ok(id));
+            #[cfg(feature = "minicbor")]
+            assert_eq!(
+                minicbor::decode::<Address>(&minicbor::to_vec(id).unwrap()).unwrap(),
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn all_encodings_roundtrip() {
+        type Roundtrip = fn(Address) -> Address;
+        let encodings: &[(&str, Roundtrip)] = &[
+            ("textual", |id| Address::from_str(&id.to_string()).unwrap()),
+            ("bytes", |id| Address::from_bytes(&id.to_vec()).unwrap()),
+            ("fixed32", |id| {
+                Address::from_fixed32(id.to_byte_array()).unwrap()
+            }),
+            ("hex", |id| Address::from_hex(&id.to_hex()).unwrap()),
+            ("proto", |id| {
+                Address::from_proto_bytes(&id.to_proto_bytes()).unwrap()
+            }),
+            ("order preserving", |id| {
+                Address::from_order_preserving_key(&id.to_order_preserving_key()).unwrap()
+            }),
+            ("padded", |id| {
+                Address::from_padded_string(&id.to_padded_string(64).unwrap()).unwrap()
+            }),
+            ("subresource suffix", |id| {
+                Address::from_str_with_subresource_suffix(&id.to_str_with_subresource_suffix())
+                    .unwrap()
+            }),
+            ("network", |id| {
+                let network = super::NetworkId::from(id);
+                super::NetworkId::from_str(&network.to_string())
+                    .unwrap()
+                    .into()
+            }),
+            ("postcard", |id| {
+                postcard::from_bytes(&postcard::to_allocvec(&id).unwrap()).unwrap()
+            }),
+            #[cfg(feature = "minicbor")]
+            ("cbor", |id| {
+                minicbor::decode(&minicbor::to_vec(id).unwrap()).unwrap()
+            }),
+        ];
+
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            for (name, roundtrip) in encodings {
+                assert_eq!(roundtrip(id), id, "{} encoding of {}", name, id);
+            }
+        }
+    }
+
+    #[test]
+    fn serde_json_and_cbor() {
+        let id = identity(1).with_subresource_id(2).unwrap();
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), id);
+
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(&ciborium::value::Value::Bytes(id.to_vec()), &mut cbor).unwrap();
+        assert_eq!(
+            ciborium::de::from_reader::<Address, _>(cbor.as_slice()).unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn serde_ron() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(3).unwrap(),
+        ] {
+            let text = ron::to_string(&id).unwrap();
+            assert_eq!(text, format!("\"{}\"", id));
+            assert_eq!(ron::from_str::<Address>(&text).unwrap(), id);
+        }
+
+        // A struct field, as in a config file.
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Config {
+            owner: Address,
+        }
+        let config = Config { owner: identity(2) };
+        let text = ron::to_string(&config).unwrap();
+        assert_eq!(text, format!("(owner:\"{}\")", identity(2)));
+        assert_eq!(ron::from_str::<Config>(&text).unwrap(), config);
+
+        // The bytes form is not accepted.
+        assert!(ron::from_str::<Address>("[0]").is_err());
+    }
+
+    #[test]
+    fn serde_cbor_bytes() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(3).unwrap(),
+        ] {
+            // A CBOR byte string of length 1 or 29-32 has a 1 or 2 byte header.
+            let mut cbor = Vec::new();
+            ciborium::ser::into_writer(&id, &mut cbor).unwrap();
+            let header = if id.is_anonymous() { 1 } else { 2 };
+            assert_eq!(cbor[0] >> 5, 2);
+            assert_eq!(&cbor[header..], id.to_vec().as_slice());
+            assert_eq!(
+                ciborium::de::from_reader::<Address, _>(cbor.as_slice()).unwrap(),
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn serde_bincode() {
+        use bincode::Options;
+
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = b.with_subresource_id(1).unwrap();
+
+        // The default options use varints, so short lengths take a byte.
+        let options = bincode::DefaultOptions::new();
+        for (id, len) in [(a, 1), (b, 29), (c, 32)] {
+            let bytes = options.serialize(&id).unwrap();
+            assert_eq!(bytes.len(), 1 + len);
+            assert_eq!(&bytes[1..], id.to_vec().as_slice());
+            assert_eq!(options.deserialize::<Address>(&bytes).unwrap(), id);
+
+            let bytes = bincode::serialize(&id).unwrap();
+            assert_eq!(bytes.len(), 8 + len);
+            assert_eq!(bincode::deserialize::<Address>(&bytes).unwrap(), id);
+        }
+
+        // Invalid bytes are rejected.
+        let mut bytes = options.serialize(&b).unwrap();
+        bytes[1] = 2;
+        assert!(options.deserialize::<Address>(&bytes).is_err());
+        assert!(options.deserialize::<Address>(&bytes[..10]).is_err());
+    }
+
+    #[test]
+    fn serde_postcard() {
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = b.with_subresource_id(1).unwrap();
+
+        for (id, len) in [(a, 1), (b, 29), (c, 32)] {
+            let bytes = postcard::to_allocvec(&id).unwrap();
+            // A single byte varint length prefix followed by the minimal bytes.
+            assert_eq!(bytes.len(), 1 + len);
+            assert_eq!(&bytes[1..], id.to_vec().as_slice());
+            assert_eq!(postcard::from_bytes::<Address>(&bytes).unwrap(), id);
         }
+    }
 
-        match bytes[0] {
-            0 => {
-                if bytes.len() > 1 {
-                    Err(ManyError::invalid_identity())
-                } else {
-                    Ok(Self::anonymous())
-                }
-            }
-            1 => {
-                if bytes.len() != 29 {
-                    Err(ManyError::invalid_identity())
-                } else {
-                    let mut slice = [0; 28];
-                    slice.copy_from_slice(&bytes[1..29]);
-                    Ok(Self::public_key(slice))
-                }
-            }
-            hi @ 0x80..=0xff => {
-                if bytes.len() != 32 {
-                    Err(ManyError::invalid_identity())
-                } else {
-                    let mut hash = [0; 28];
-                    let mut subid = [0; 4];
-                    hash.copy_from_slice(&bytes[1..29]);
-                    subid[0] = hi;
-                    subid[1..].copy_from_slice(&bytes[29..32]);
-                    Ok(Self::subresource_unchecked(
-                        hash,
-                        SubresourceId(u32::from_be_bytes(subid)),
-                    ))
-                }
-            }
-            x => Err(ManyError::invalid_identity_kind(x.to_string())),
+    #[test]
+    fn bits() {
+        let id = identity(1);
+        let bits: Vec<bool> = id.bits().collect();
+        assert_eq!(bits.len(), 256);
+
+        // The first byte is 0x01, the last byte of the hash is 0x01, the
+        // remaining 3 bytes are unused for public keys.
+        assert_eq!(
+            &bits[0..8],
+            &[false, false, false, false, false, false, false, true]
+        );
+        assert!(bits[28 * 8 + 7]);
+        assert!(!bits[255]);
+
+        for (i, b) in bits.iter().enumerate() {
+            assert_eq!(id.bit(i), Some(*b));
         }
+        assert_eq!(id.bit(256), None);
     }
 
-    pub fn from_str(value: &str) -> Result<Self, ManyError> {
-        if !value.starts_with('m') {
-            return Err(ManyError::invalid_identity_prefix(value[0..0].to_string()));
+    #[test]
+    fn order_preserving_key() {
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = b.with_subresource_id(0x0100_0000).unwrap();
+        let d = identity(2).with_subresource_id(1).unwrap();
+
+        // c has higher bits in its subresource ID, but d has a higher hash.
+        assert!(a < b);
+        assert!(b < c);
+        assert!(c < d);
+
+        for id in [a, b, c, d] {
+            let key = id.to_order_preserving_key();
+            assert_eq!(Address::from_order_preserving_key(&key), Ok(id));
         }
 
-        // Prevent subtract with overflow in the next block
-        if value.len() < 3 {
-            return Err(ManyError::invalid_identity());
+        // Public keys cannot have a subresource ID.
+        let mut key = b.to_order_preserving_key();
+        key[32] = 1;
+        assert!(Address::from_order_preserving_key(&key).is_err());
+    }
+
+    fn arb_address() -> impl proptest::strategy::Strategy<Value = Address> {
+        use proptest::prelude::*;
+
+        (0u8..3, any::<[u8; 28]>(), 0..=super::MAX_SUBRESOURCE_ID).prop_map(
+            |(kind, hash, subid)| match kind {
+                0 => Address::anonymous(),
+                1 => Address::public_key_unchecked(hash),
+                _ => Address::public_key_unchecked(hash)
+                    .with_subresource_id(subid)
+                    .unwrap(),
+            },
+        )
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn order_preserving_key_sort(mut ids in proptest::collection::vec(arb_address(), 0..32)) {
+            let mut keys: Vec<_> = ids.iter().map(|id| id.to_order_preserving_key()).collect();
+            ids.sort();
+            keys.sort();
+
+            let sorted: Vec<_> = ids.iter().map(|id| id.to_order_preserving_key()).collect();
+            assert_eq!(sorted, keys);
         }
 
-        if &value[1..] == "aa" || &value[1..] == "aaaa" {
-            Ok(Self::anonymous())
-        } else {
-            let data = &value[..value.len() - 2][1..];
-            let data = base32::decode(base32::Alphabet::RFC4648 { padding: false }, data).unwrap();
-            let result = Self::try_from(data.as_slice())?;
+        #[test]
+        fn collation_key_sort(mut ids in proptest::collection::vec(arb_address(), 0..32)) {
+            let mut keys: Vec<_> = ids.iter().map(|id| id.collation_key()).collect();
+            ids.sort();
+            keys.sort();
 
-            if result.to_string() != value {
-                Err(ManyError::invalid_identity())
-            } else {
-                Ok(result)
+            let sorted: Vec<_> = ids.iter().map(|id| id.collation_key()).collect();
+            assert_eq!(sorted, keys);
+        }
+    }
+
+    #[test]
+    fn eq() {
+        let a = Address::anonymous();
+        let b = identity(1);
+        let c = b.with_subresource_id(1).unwrap();
+        let d = b.with_subresource_id(0x0100_0001).unwrap();
+        let all = [
+            a,
+            b,
+            c,
+            d,
+            identity(2),
+            identity(2).with_subresource_id(1).unwrap(),
+        ];
+
+        for (i, x) in all.iter().enumerate() {
+            for (j, y) in all.iter().enumerate() {
+                assert_eq!(x == y, i == j, "{:?} {:?}", x, y);
             }
         }
+        assert_eq!(b, Address::from_str(&b.to_string()).unwrap());
+        assert_eq!(c, Address::from_bytes(&c.to_vec()).unwrap());
+
+        assert!(c.matches(&b));
+        assert!(c.matches(&d));
+        assert!(!c.matches(&identity(2)));
+        assert!(!c.matches(&a));
+        assert!(a.matches(&a));
     }
 
-    pub const fn to_byte_array(self) -> [u8; MAX_IDENTITY_BYTE_LEN] {
-        self.bytes
+    #[test]
+    fn parent() {
+        let id = identity(1);
+        assert_eq!(id.with_subresource_id(7).unwrap().parent(), Some(id));
+        assert_eq!(id.with_subresource_id(0).unwrap().parent(), Some(id));
+        assert_eq!(id.parent(), Some(id));
+        assert_eq!(Address::anonymous().parent(), None);
     }
 
-    #[rustfmt::skip]
-    pub fn to_vec(self) -> Vec<u8> {
-        // This makes sure we actually have a Vec<u8> that's smaller than 32 bytes if
-        // it can be.
-        match self.bytes[0] {
-            0 => vec![0],
-            1 => {
-                let pk = &self.bytes[1..=SHA_OUTPUT_SIZE];
-                vec![
-                    1,
-                    pk[ 0], pk[ 1], pk[ 2], pk[ 3], pk[ 4], pk[ 5], pk[ 6], pk[ 7],
-                    pk[ 8], pk[ 9], pk[10], pk[11], pk[12], pk[13], pk[14], pk[15],
-                    pk[16], pk[17], pk[18], pk[19], pk[20], pk[21], pk[22], pk[23],
-                    pk[24], pk[25], pk[26], pk[27],
-                ]
-            }
-            0x80..=0xFF => {
-                self.bytes.to_vec()
+    #[test]
+    fn subresources() {
+        use super::MAX_SUBRESOURCE_ID;
+
+        let id = identity(1);
+        let expected: Vec<_> = (0..5).map(|i| id.with_subresource_id(i).unwrap()).collect();
+        assert_eq!(id.subresources(0..5).collect::<Vec<_>>(), expected);
+        assert_eq!(expected[2].subresources(0..5).collect::<Vec<_>>(), expected);
+
+        assert_eq!(Address::anonymous().subresources(0..5).count(), 0);
+        assert_eq!(id.subresources(5..5).count(), 0);
+
+        let last: Vec<_> = id.subresources(MAX_SUBRESOURCE_ID - 1..u32::MAX).collect();
+        assert_eq!(
+            last,
+            vec![
+                id.with_subresource_id(MAX_SUBRESOURCE_ID - 1).unwrap(),
+                id.with_subresource_id(MAX_SUBRESOURCE_ID).unwrap(),
+            ]
+        );
+        assert_eq!(id.subresources(0x8000_0000..u32::MAX).count(), 0);
+    }
+
+    #[test]
+    fn eq_reserved() {
+        let reserved = |bytes: [u8; 32]| super::Address(super::InnerAddress { bytes });
+        let mut bytes = [0x42; 32];
+        let a = reserved(bytes);
+        assert_eq!(a, reserved(bytes));
+
+        bytes[31] = 0;
+        assert_ne!(a, reserved(bytes));
+        bytes[0] = 0x43;
+        assert_ne!(reserved(bytes), reserved([0x42; 32]));
+        assert_ne!(a, Address::anonymous());
+        assert_ne!(a, identity(1));
+
+        // The order agrees with equality.
+        let ids = [a, reserved(bytes), reserved([0x42; 32]), identity(1)];
+        for x in ids {
+            for y in ids {
+                assert_eq!(x == y, x.cmp(&y) == std::cmp::Ordering::Equal);
             }
-            _ => unreachable!(),
         }
+        assert_eq!(std::collections::BTreeSet::from(ids).len(), 3);
+        assert!(identity(1) < a);
     }
 
-    pub const fn is_anonymous(&self) -> bool {
-        self.bytes[0] == 0
+    #[test]
+    fn illegal() {
+        use many_error::ManyErrorCode;
+
+        let mut bytes = identity(1).with_subresource_id(1).unwrap().to_byte_array();
+        bytes[0] = 0x42;
+        let illegal = super::Address(super::InnerAddress { bytes });
+        assert!(illegal.is_illegal());
+        assert!(!illegal.is_valid_source());
+        assert_eq!(illegal.to_vec(), bytes.to_vec());
+
+        // Every decoder rejects the reserved kind byte.
+        let code = |r: Result<Address, many_error::ManyError>| r.unwrap_err().code();
+        assert_eq!(
+            code(Address::from_bytes(&illegal.to_vec())),
+            ManyErrorCode::InvalidIdentityKind
+        );
+        assert_eq!(
+            code(Address::from_fixed32(bytes)),
+            ManyErrorCode::InvalidIdentityKind
+        );
+        assert_eq!(
+            code(Address::from_hex(&illegal.to_hex())),
+            ManyErrorCode::InvalidIdentityKind
+        );
+        assert!(Address::from_str(&illegal.to_string()).is_err());
+        #[cfg(feature = "minicbor")]
+        assert!(minicbor::decode::<Address>(&minicbor::to_vec(illegal).unwrap()).is_err());
+
+        #[cfg(feature = "corpus")]
+        for id in super::address_corpus() {
+            assert!(!id.is_illegal());
+        }
     }
-    pub const fn is_public_key(&self) -> bool {
-        self.bytes[0] == 1
+
+    #[test]
+    fn hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashMap;
+        use std::hash::{Hash, Hasher};
+
+        fn hash_of(id: &Address) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            id.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let id = identity(1);
+        let parsed = Address::from_str(&id.to_string()).unwrap();
+        assert_eq!(hash_of(&id), hash_of(&parsed));
+
+        // Bytes `eq` ignores must not change the hash.
+        let mut bytes = identity(1).to_byte_array();
+        bytes[31] = 1;
+        let padded = super::Address(super::InnerAddress { bytes });
+        assert_eq!(padded, id);
+        assert_eq!(hash_of(&padded), hash_of(&id));
+        let mut bytes = [0; 32];
+        bytes[1] = 1;
+        let anonymous = super::Address(super::InnerAddress { bytes });
+        assert_eq!(hash_of(&anonymous), hash_of(&Address::anonymous()));
+
+        let mut map = HashMap::new();
+        map.insert(id, "account");
+        assert_eq!(map.get(&parsed), Some(&"account"));
+        assert_eq!(map.get(&id.with_subresource_id(1).unwrap()), None);
     }
-    pub const fn is_subresource(&self) -> bool {
-        matches!(self.bytes[0], 0x80..=0xFF)
+
+    #[test]
+    fn collation_key() {
+        let b = identity(1);
+        let c = b.with_subresource_id(0x0100_0000).unwrap();
+
+        assert_eq!(Address::anonymous().collation_key(), vec![0]);
+        assert_eq!(b.collation_key().len(), 29);
+        assert_eq!(c.collation_key(), c.to_order_preserving_key().to_vec());
+        assert!(b.collation_key() < c.collation_key());
     }
 
-    pub const fn subresource_id(&self) -> Option<u32> {
-        match self.bytes[0] {
-            x @ 0x80..=0xFF => {
-                let high = ((x & 0x7F) as u32) << 24;
-                let mut low = (self.bytes[SHA_OUTPUT_SIZE + 1] as u32) << 16;
-                low += (self.bytes[SHA_OUTPUT_SIZE + 2] as u32) << 8;
-                low += self.bytes[SHA_OUTPUT_SIZE + 3] as u32;
-                Some(high + low)
-            }
-            _ => None,
-        }
+    #[test]
+    fn check_dest() {
+        use many_error::ManyErrorCode;
+
+        assert_eq!(identity(1).check_dest(), Ok(()));
+        assert_eq!(
+            identity(1).with_subresource_id(1).unwrap().check_dest(),
+            Ok(())
+        );
+        assert_eq!(
+            Address::anonymous().check_dest().unwrap_err().code(),
+            ManyErrorCode::AnonymousCannotBeDestination
+        );
+
+        let mut bytes = [0; 32];
+        bytes[0] = 2;
+        let illegal = super::Address(super::InnerAddress { bytes });
+        assert_eq!(
+            illegal.check_dest().unwrap_err().code(),
+            ManyErrorCode::InvalidIdentityKind
+        );
     }
 
-    pub const fn hash(&self) -> Option<[u8; SHA_OUTPUT_SIZE]> {
-        match self.bytes[0] {
-            1 | 0x80..=0xFF => {
-                let mut hash = [0; SHA_OUTPUT_SIZE];
-                let mut len = SHA_OUTPUT_SIZE;
-                while len > 0 {
-                    len -= 1;
-                    hash[len] = self.bytes[1 + len];
-                }
-                Some(hash)
+    #[test]
+    fn default_account() {
+        let id = identity(1);
+        let account = id.default_account();
+        assert!(account.is_subresource());
+        assert_eq!(account.subresource_id(), Some(0));
+        assert!(account.is_default_account());
+        assert!(account.matches(&id));
+        assert!(!id.is_default_account());
+
+        // Subresources and anonymous are left untouched.
+        let sub = id.with_subresource_id(5).unwrap();
+        assert_eq!(sub.default_account(), sub);
+        assert!(!sub.is_default_account());
+        assert_eq!(account.default_account(), account);
+        assert_eq!(Address::anonymous().default_account(), Address::anonymous());
+    }
+
+    #[test]
+    fn render_addresses() {
+        let ids = [
+            identity(1),
+            Address::anonymous(),
+            identity(2).with_subresource_id(3).unwrap(),
+        ];
+
+        let mut out = Vec::new();
+        super::render_addresses(&ids, ",\n", &mut out).unwrap();
+        let expected: Vec<String> = ids.iter().map(Address::to_string).collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected.join(",\n"));
+
+        let mut out = Vec::new();
+        super::render_addresses(&[], ",", &mut out).unwrap();
+        assert!(out.is_empty());
+
+        // Errors of the writer are returned.
+        let mut full = [0u8; 10];
+        let err = super::render_addresses(&ids, ",", &mut &mut full[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn address_set_seed() {
+        let a = identity(1);
+        let b = identity(2).with_subresource_id(3).unwrap();
+        let c = Address::anonymous();
+
+        let seed = super::address_set_seed(&[a, b, c]);
+        assert_eq!(seed, super::address_set_seed(&[c, a, b]));
+        assert_eq!(seed, super::address_set_seed(&[b, c, a, b]));
+
+        assert_ne!(seed, super::address_set_seed(&[a, b]));
+        assert_ne!(seed, super::address_set_seed(&[a, identity(2), c]));
+    }
+
+    #[test]
+    fn eq_str() {
+        let id = identity(1);
+        let s = id.to_string();
+        let upper = s.to_ascii_uppercase();
+
+        assert!(id == s.as_str());
+        assert!(id.eq_str_canonical(&s));
+        assert!(id.eq_str_lenient(&s));
+
+        assert!(id != upper.as_str());
+        assert!(!id.eq_str_canonical(&upper));
+        assert!(id.eq_str_lenient(&upper));
+        assert!(id.eq_str_lenient(&format!(" {} ", s)));
+
+        assert!(!Address::anonymous().eq_str_canonical("maaaa"));
+        assert!(Address::anonymous().eq_str_lenient("MAAAA"));
+        assert!(!id.eq_str_lenient(&identity(2).to_string()));
+        assert!(!id.eq_str_lenient("garbage"));
+    }
+
+    #[test]
+    fn write_textual() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(2).with_subresource_id(3).unwrap(),
+        ] {
+            let mut s = String::new();
+            id.write_textual(&mut s).unwrap();
+            assert_eq!(s, id.to_string());
+
+            // The allocating encoding the writer replaces.
+            if !id.is_anonymous() {
+                let data = id.to_vec();
+                let mut crc = crc_any::CRCu16::crc16();
+                crc.digest(&data);
+                let alphabet = base32::Alphabet::RFC4648 { padding: false };
+                let expected = format!(
+                    "m{}{}",
+                    base32::encode(alphabet, &data),
+                    &base32::encode(alphabet, &crc.get_crc().to_be_bytes())[..2],
+                );
+                assert_eq!(s, expected.to_ascii_lowercase());
             }
-            _ => None,
+
+            let mut h = heapless::String::<64>::new();
+            id.write_textual(&mut h).unwrap();
+            assert_eq!(h.as_str(), s);
         }
     }
-}
 
-impl std::fmt::Display for InnerAddress {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if self.is_anonymous() {
-            // Special case this.
-            return write!(f, "maa");
+    #[test]
+    fn as_significant_bytes() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(2).with_subresource_id(3).unwrap(),
+        ] {
+            assert_eq!(id.as_significant_bytes(), id.to_vec().as_slice());
+            assert_eq!(Address::from_bytes(id.as_significant_bytes()).unwrap(), id);
+
+            let v = heapless::Vec::<u8, 32>::from_slice(id.as_significant_bytes()).unwrap();
+            assert_eq!(Address::from_bytes(&v).unwrap(), id);
         }
+    }
 
-        let data = self.to_vec();
-        let mut crc = crc_any::CRCu16::crc16();
-        crc.digest(&data);
-
-        let crc = crc.get_crc().to_be_bytes();
-        write!(
-            f,
-            "m{}{}",
-            base32::encode(base32::Alphabet::RFC4648 { padding: false }, &data)
-                .to_ascii_lowercase(),
-            base32::encode(base32::Alphabet::RFC4648 { padding: false }, &crc)
-                .get(0..2)
-                .unwrap()
-                .to_ascii_lowercase(),
-        )
+    #[test]
+    fn const_constructors() {
+        const ANONYMOUS: Address = Address::anonymous();
+        const PUBLIC_KEY: Address = Address::public_key_unchecked([1; 28]);
+        const SUBRESOURCE: Address =
+            PUBLIC_KEY.with_subresource_id_unchecked(super::SubresourceId(3));
+        const BYTES: [u8; 32] = SUBRESOURCE.to_byte_array();
+
+        assert!(ANONYMOUS.is_anonymous());
+        assert_eq!(SUBRESOURCE.parent(), Some(PUBLIC_KEY));
+        assert_eq!(Address::from_fixed32(BYTES), Ok(SUBRESOURCE));
     }
-}
 
-impl TryFrom<String> for InnerAddress {
-    type Error = ManyError;
+    #[test]
+    fn fixed_buffer_conversions() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(2).with_subresource_id(3).unwrap(),
+        ] {
+            let bytes = id.to_byte_array();
+            let len = id.as_significant_bytes().len();
+            assert_eq!(Address::from_fixed32(bytes), Ok(id));
+            assert_eq!(Address::from_bytes(&bytes[..len]), Ok(id));
+
+            let mut s = heapless::String::<64>::new();
+            id.write_textual(&mut s).unwrap();
+            assert_eq!(Address::from_str(&s), Ok(id));
+            assert_eq!(Address::from_str_checked(&s), Ok(id));
+        }
+    }
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        InnerAddress::from_str(value.as_str())
+    #[test]
+    fn from_base32() {
+        use super::InnerAddress;
+
+        let id = identity(1).with_subresource_id(2).unwrap();
+        let textual = id.to_string();
+        let body = &textual[1..textual.len() - 2];
+        assert_eq!(InnerAddress::from_base32(body).map(super::Address), Ok(id));
+        assert_eq!(
+            InnerAddress::from_base32(&body.to_ascii_uppercase()).map(super::Address),
+            Ok(id)
+        );
+
+        // Invalid characters, and too much data for any identity.
+        assert!(InnerAddress::from_base32(&format!("{}1", body)).is_err());
+        assert!(InnerAddress::from_base32(&format!("{}aa", body)).is_err());
+        assert!(InnerAddress::from_base32(&"a".repeat(1000)).is_err());
+        assert!(InnerAddress::from_base32("").is_err());
     }
-}
 
-impl TryFrom<&[u8]> for InnerAddress {
-    type Error = ManyError;
+    #[test]
+    fn to_emoji() {
+        let a = identity(1);
+        let b = Address::from_str(&a.to_string()).unwrap();
+
+        for count in [0, 1, 8, 32, 33, 100] {
+            let emoji = a.to_emoji(count);
+            assert_eq!(emoji, b.to_emoji(count));
+            assert_eq!(emoji.chars().count(), count);
+            assert!(emoji
+                .chars()
+                .all(|c| super::emoji::EMOJI.contains(&c.to_string().as_str())));
+        }
 
-    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        Self::from_bytes(bytes)
+        assert!(a.to_emoji(40).starts_with(&a.to_emoji(33)));
+        assert_ne!(a.to_emoji(8), identity(2).to_emoji(8));
     }
-}
 
-#[cfg(test)]
-pub mod tests {
-    use crate::testing::identity;
-    use crate::Address;
-    use serde_test::{assert_tokens, Configure, Token};
-    use std::str::FromStr;
+    #[test]
+    fn safety_words() {
+        let a = identity(1);
+        let b = Address::from_str(&a.to_string()).unwrap();
+
+        for count in [0, 1, 3, 23, 24, 50] {
+            let words = a.safety_words(count);
+            assert_eq!(words.len(), count);
+            assert_eq!(words, b.safety_words(count));
+            assert!(words
+                .iter()
+                .all(|w| super::wordlist::BIP39_ENGLISH.contains(&w.as_str())));
+        }
+
+        // Asking for more words extends the list.
+        assert_eq!(a.safety_words(3), a.safety_words(30)[..3]);
+        assert_ne!(a.safety_words(6), identity(2).safety_words(6));
+    }
 
     #[test]
-    fn can_read_anonymous() {
-        let a = Address::anonymous();
-        let a_str = a.to_string();
-        let a2 = Address::from_str(&a_str).unwrap();
+    fn to_qr_segments() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(2).with_subresource_id(3).unwrap(),
+        ] {
+            let segments = id.to_qr_segments();
+            assert!(segments
+                .iter()
+                .all(|s| s.mode == super::QrMode::Alphanumeric));
+            let joined: String = segments.iter().map(|s| s.data.as_str()).collect();
+            assert_eq!(joined, id.to_string().to_uppercase());
+        }
+    }
 
-        assert_eq!(a, a2);
+    #[cfg(feature = "coset")]
+    fn cose_key(x: u8) -> coset::CoseKey {
+        coset::CoseKeyBuilder::new_ec2_pub_key(
+            coset::iana::EllipticCurve::P_256,
+            vec![x; 32],
+            vec![x; 32],
+        )
+        .build()
     }
 
+    #[cfg(feature = "coset")]
     #[test]
-    fn can_read_anonymous_short() {
-        assert_eq!(Address::from_str("maa"), Ok(Address::anonymous()));
+    fn from_cose_key_owned() {
+        let borrowed = crate::cose::address_unchecked(&cose_key(1)).unwrap();
+        assert_eq!(Address::from_cose_key_owned(cose_key(1)), Ok(borrowed));
+        assert_eq!(Address::try_from(cose_key(1)), Ok(borrowed));
+        assert_ne!(Address::try_from(cose_key(2)), Ok(borrowed));
     }
 
+    #[cfg(feature = "coset")]
     #[test]
-    fn byte_array_conversion() {
-        let a = Address::anonymous();
-        let b = identity(1);
-        let c = identity(2);
+    fn matches_key() {
+        let key = cose_key(1);
+        let id = crate::cose::address_unchecked(&key).unwrap();
+
+        assert!(id.matches_key(Some(&key)));
+        assert!(id.with_subresource_id(3).unwrap().matches_key(Some(&key)));
+        assert!(!id.matches_key(Some(&cose_key(2))));
+        assert!(!id.matches_key(None));
+        assert!(Address::anonymous().matches_key(None));
+        assert!(!Address::anonymous().matches_key(Some(&key)));
+    }
 
-        assert_ne!(a.to_string(), b.to_string());
-        assert_ne!(b.to_string(), c.to_string());
-        assert_ne!(a.to_vec(), b.to_vec());
-        assert_ne!(b.to_vec(), c.to_vec());
+    #[cfg(feature = "coset")]
+    #[test]
+    fn eq_cose_key() {
+        let key = cose_key(1);
+        let id = crate::cose::address_unchecked(&key).unwrap();
+
+        assert_eq!(id, key);
+        assert_eq!(id.with_subresource_id(3).unwrap(), key);
+        assert_ne!(id, cose_key(2));
+        assert_ne!(Address::anonymous(), key);
+    }
 
-        assert_eq!(Address::from_str(&a.to_string()), Ok(a));
-        assert_eq!(Address::from_str(&b.to_string()), Ok(b));
-        assert_eq!(Address::from_str(&c.to_string()), Ok(c));
+    #[cfg(feature = "coset")]
+    #[test]
+    fn matches_key_returning_hash() {
+        let key = cose_key(1);
+        let id = crate::cose::address_unchecked(&key).unwrap();
+        let expected = id.0.hash().unwrap();
+
+        let sub = id.with_subresource_id(3).unwrap();
+        assert_eq!(sub.matches_key_returning_hash(&key), Ok((true, expected)));
+        assert!(sub.matches_key_hash(&expected));
+
+        let (matched, hash) = identity(2).matches_key_returning_hash(&key).unwrap();
+        assert!(!matched);
+        assert_eq!(hash, expected);
+        assert!(!Address::anonymous().matches_key_hash(&hash));
     }
 
+    #[cfg(feature = "coset")]
     #[test]
-    fn textual_format_1() {
-        let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys").unwrap();
-        let b = Address::from_bytes(
-            &hex::decode("01c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22").unwrap(),
-        )
-        .unwrap();
+    fn authorize_under() {
+        use many_error::ManyErrorCode;
+
+        let key = cose_key(1);
+        let owner = crate::cose::address_unchecked(&key).unwrap();
+        let sub = owner.with_subresource_id(1).unwrap();
+
+        assert_eq!(owner.authorize_under(&owner, Some(&key)), Ok(()));
+        assert_eq!(sub.authorize_under(&owner, Some(&key)), Ok(()));
+
+        let other = identity(1).with_subresource_id(1).unwrap();
+        assert_eq!(
+            other
+                .authorize_under(&owner, Some(&key))
+                .unwrap_err()
+                .code(),
+            ManyErrorCode::IdentityMismatch
+        );
+        assert_eq!(
+            sub.authorize_under(&owner, Some(&cose_key(2)))
+                .unwrap_err()
+                .code(),
+            ManyErrorCode::PublicKeyMismatch
+        );
+        assert_eq!(
+            sub.authorize_under(&owner, None).unwrap_err().code(),
+            ManyErrorCode::PublicKeyMismatch
+        );
+    }
 
-        assert_eq!(a, b);
+    #[test]
+    fn try_subresource() {
+        let hash = [1; 28];
+        let max = super::MAX_SUBRESOURCE_ID;
+        let sub = Address::try_subresource(hash, max).unwrap();
+        assert_eq!(sub.subresource_id(), Some(0x7FFF_FFFF));
+
+        assert_eq!(
+            Address::try_subresource(hash, 0x8000_0000)
+                .unwrap_err()
+                .code(),
+            many_error::ManyErrorCode::InvalidIdentitySubResourceId
+        );
+        assert!(Address::try_subresource(hash, 0x8000_0001).is_err());
+        assert_eq!(
+            Address::try_subresource(hash, 1).unwrap().subresource_id(),
+            Some(1)
+        );
     }
 
     #[test]
-    fn textual_format_2() {
-        let a =
-            Address::from_str("mqbfbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wiaaaaqnz").unwrap();
-        let b = Address::from_bytes(
-            &hex::decode("804a101d521d810211a0c6346ba89bd1cc1f821c03b969ff9d5c8b2f59000001")
-                .unwrap(),
-        )
-        .unwrap();
+    fn kind() {
+        use super::AddressKind;
+
+        assert_eq!(Address::anonymous().kind(), Some(AddressKind::Anonymous));
+        assert_eq!(identity(1).kind(), Some(AddressKind::PublicKey));
+        let sub = identity(1).with_subresource_id(0x7000_0001).unwrap();
+        assert_eq!(sub.kind(), Some(AddressKind::Subresource(0x7000_0001)));
+
+        assert_eq!(
+            format!("{:?}", sub),
+            format!("Identity(\"subresource(1879048193)\", \"{}\")", sub)
+        );
+        assert_eq!(AddressKind::PublicKey.to_string(), "public-key");
+
+        let mut bytes = sub.to_byte_array();
+        bytes[0] = 0x42;
+        let reserved = super::Address(super::InnerAddress { bytes });
+        assert_eq!(reserved.kind(), None);
+        assert!(format!("{:?}", reserved).starts_with("Identity(\"??\""));
+        assert!(super::TracingField(reserved).to_string().starts_with("??:"));
+    }
 
-        assert_eq!(a, b);
+    #[test]
+    fn as_ref() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let bytes: &[u8; 32] = id.as_ref();
+            assert_eq!(bytes, &id.to_byte_array());
+        }
     }
 
     #[test]
-    fn subresource_1() {
-        let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys")
-            .unwrap()
-            .with_subresource_id(1)
-            .unwrap();
-        let b = Address::from_bytes(
-            &hex::decode("80c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22000001")
-                .unwrap(),
-        )
-        .unwrap();
-        let c = Address::from_bytes(
-            &hex::decode("80c8aead03f915f128f0fa7ff696c656eaa93db87bd9aa73df693acb22000002")
-                .unwrap(),
-        )
-        .unwrap();
+    fn is_anonymous_str() {
+        assert!(Address::is_anonymous_str("maa"));
+        assert!(Address::is_anonymous_str(" maa \n"));
+        assert!(Address::is_anonymous_str(&Address::anonymous().to_string()));
 
-        assert_eq!(a, b);
-        assert_eq!(b.with_subresource_id(2).unwrap(), c);
+        for s in ["", "maaa", "MAA", "m aa"] {
+            assert!(!Address::is_anonymous_str(s));
+        }
+        assert!(!Address::is_anonymous_str(&identity(1).to_string()));
     }
 
-    proptest::proptest! {
-        #[test]
-        fn subresource_id_fuzzy(subid: u32) {
-            let a = Address::from_str("mahek5lid7ek7ckhq7j77nfwgk3vkspnyppm2u467ne5mwiqys")
-                .unwrap()
-                .with_subresource_id(subid);
+    #[test]
+    fn hex() {
+        assert_eq!(Address::anonymous().to_hex(), "00");
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let hex = id.to_hex();
+            assert_eq!(hex.len(), id.to_vec().len() * 2);
+            assert_eq!(Address::from_hex(&hex), Ok(id));
+        }
 
-            if let Ok(id) = a {
-                let b = Address::from_str(&id.to_string());
-                assert_eq!(a, b);
-            } else {
-                assert_eq!(subid.leading_zeros(), 0);
-            }
+        let hex = identity(1).to_hex();
+        for bad in [&hex[1..], "0", "zz", &hex.replace('1', "g")] {
+            assert_eq!(
+                Address::from_hex(bad),
+                Err(many_error::ManyError::invalid_identity())
+            );
         }
     }
 
     #[test]
-    fn serde_anonymous() {
-        let id = Address::anonymous();
-        assert_tokens(&id.readable(), &[Token::String("maa")]);
-        assert_tokens(&id.compact(), &[Token::Bytes(&[0])]);
+    fn from_str_malformed() {
+        for bad in ["", "x", "\u{e9}"] {
+            assert!(Address::from_str(bad).is_err());
+        }
+
+        let s = identity(1).to_string();
+        for bad in [
+            "m".to_string(),
+            "mx".to_string(),
+            format!("m!{}", &s[2..]),
+            format!("m0{}", &s[2..]),
+            format!("m\u{e9}{}", &s[3..]),
+            format!("{}\u{e9}", &s[..s.len() - 1]),
+        ] {
+            assert_eq!(
+                Address::from_str(&bad),
+                Err(many_error::ManyError::invalid_identity()),
+                "{}",
+                bad
+            );
+        }
     }
 
     #[test]
@@ -626,5 +3742,23 @@ pub mod tests {
         assert!(Address::from_str("m").is_err());
         assert!(Address::from_str("ma").is_err());
         assert!(Address::from_str("maa").is_ok());
+        assert!(Address::from_str("maaa").is_err());
+        assert!(Address::from_str("mab").is_err());
+        assert!(Address::from_str("maaaaa").is_err());
+        assert_eq!(Address::from_str("maaaa"), Ok(Address::anonymous()));
+    }
+
+    #[test]
+    fn error_fields() {
+        let id = identity(1).to_string();
+
+        let err = Address::from_str(&format!("x{}", &id[1..])).unwrap_err();
+        assert_eq!(err.invalid_identity_prefix_str(), Some("x"));
+        let err = Address::from_str_checked(&format!("x{}", &id[1..])).unwrap_err();
+        assert_eq!(err.invalid_identity_prefix_str(), Some("x"));
+
+        let err = Address::from_bytes(&[0x42]).unwrap_err();
+        assert_eq!(err.invalid_identity_kind_byte(), Some(0x42));
+        assert!(err.code().is_identity_error());
     }
 }
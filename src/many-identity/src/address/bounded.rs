@@ -0,0 +1,91 @@
+use crate::Address;
+use core::ops::Deref;
+use many_error::ManyError;
+
+/// An address whose subresource ID, if any, is at most `MAX`. This lets
+/// deployments cap subresource IDs (e.g. at 2^24) in their types.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[must_use]
+pub struct BoundedAddress<const MAX: u32>(Address);
+
+impl<const MAX: u32> BoundedAddress<MAX> {
+    pub const fn address(&self) -> &Address {
+        &self.0
+    }
+
+    /// Returns the subresource `subid` of this address, erroring if it is
+    /// above `MAX`.
+    pub fn with_subresource_id(&self, subid: u32) -> Result<Self, ManyError> {
+        if subid > MAX {
+            return Err(ManyError::invalid_identity_subid());
+        }
+        self.0.with_subresource_id(subid).map(Self)
+    }
+}
+
+impl<const MAX: u32> TryFrom<Address> for BoundedAddress<MAX> {
+    type Error = ManyError;
+
+    fn try_from(address: Address) -> Result<Self, Self::Error> {
+        match address.subresource_id() {
+            Some(id) if id > MAX => Err(ManyError::invalid_identity_subid()),
+            _ => Ok(Self(address)),
+        }
+    }
+}
+
+impl<const MAX: u32> From<BoundedAddress<MAX>> for Address {
+    fn from(address: BoundedAddress<MAX>) -> Self {
+        address.0
+    }
+}
+
+impl<const MAX: u32> Deref for BoundedAddress<MAX> {
+    type Target = Address;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const MAX: u32> core::fmt::Display for BoundedAddress<MAX> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+
+    #[test]
+    fn with_subresource_id() {
+        let id = BoundedAddress::<1000>::try_from(identity(1)).unwrap();
+
+        let sub = id.with_subresource_id(1000).unwrap();
+        assert_eq!(
+            Address::from(sub),
+            identity(1).with_subresource_id(1000).unwrap()
+        );
+        assert_eq!(
+            id.with_subresource_id(1001),
+            Err(ManyError::invalid_identity_subid())
+        );
+    }
+
+    #[test]
+    fn try_from() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(1000).unwrap(),
+        ] {
+            assert_eq!(*BoundedAddress::<1000>::try_from(id).unwrap(), id);
+        }
+
+        let sub = identity(1).with_subresource_id(1001).unwrap();
+        assert!(BoundedAddress::<1000>::try_from(sub).is_err());
+        assert!(BoundedAddress::<1001>::try_from(sub).is_ok());
+    }
+}
@@ -0,0 +1,55 @@
+use many_error::ManyError;
+
+impl crate::Address {
+    /// Reads the addresses in the column named `column` of a CSV with a
+    /// header row. Cells are parsed with [crate::Address::from_str_normalized].
+    /// Errors include the row number, counting the header as row 1.
+    pub fn from_csv_column(csv: &str, column: &str) -> Result<Vec<Self>, ManyError> {
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let index = reader
+            .headers()
+            .map_err(|e| ManyError::unknown(format!("Invalid CSV header: {}", e)))?
+            .iter()
+            .position(|h| h.trim() == column)
+            .ok_or_else(|| ManyError::unknown(format!("Column {} not found.", column)))?;
+
+        reader
+            .records()
+            .enumerate()
+            .map(|(i, record)| {
+                let row = i + 2;
+                let record = record
+                    .map_err(|e| ManyError::unknown(format!("Invalid CSV row {}: {}", row, e)))?;
+                let cell = record.get(index).unwrap_or_default();
+                Self::from_str_normalized(cell)
+                    .map(|(address, _)| address)
+                    .map_err(|e| {
+                        ManyError::unknown(format!("Invalid identity at row {}: {}", row, e))
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::identity;
+    use crate::Address;
+
+    #[test]
+    fn column() {
+        let a = identity(1);
+        let b = identity(2).with_subresource_id(3).unwrap();
+        let csv = format!("name,address\nalice,{}\nbob, {} \n", a, b);
+        assert_eq!(Address::from_csv_column(&csv, "address"), Ok(vec![a, b]));
+
+        assert!(Address::from_csv_column(&csv, "missing").is_err());
+    }
+
+    #[test]
+    fn malformed_row() {
+        let csv = format!("name,address\nalice,{}\nbob,not-an-address\n", identity(1));
+        let err = Address::from_csv_column(&csv, "address").unwrap_err();
+        assert!(err.to_string().contains("row 3"), "{}", err);
+    }
+}
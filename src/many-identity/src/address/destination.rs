@@ -0,0 +1,73 @@
+use crate::Address;
+use core::ops::Deref;
+use many_error::ManyError;
+
+/// An address that can be used as the destination of a message, i.e. a
+/// public key or a subresource. APIs taking a `Destination` cannot be given
+/// the anonymous address.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[must_use]
+pub struct Destination(Address);
+
+impl Destination {
+    pub const fn address(&self) -> &Address {
+        &self.0
+    }
+}
+
+impl TryFrom<Address> for Destination {
+    type Error = ManyError;
+
+    fn try_from(address: Address) -> Result<Self, Self::Error> {
+        address.check_dest()?;
+        Ok(Self(address))
+    }
+}
+
+impl From<Destination> for Address {
+    fn from(destination: Destination) -> Self {
+        destination.0
+    }
+}
+
+impl Deref for Destination {
+    type Target = Address;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl core::fmt::Display for Destination {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+
+    #[test]
+    fn anonymous_is_rejected() {
+        assert_eq!(
+            Destination::try_from(Address::anonymous()),
+            Err(ManyError::anonymous_cannot_be_destination())
+        );
+    }
+
+    #[test]
+    fn public_key_and_subresource() {
+        let id = identity(1);
+        let dest = Destination::try_from(id).unwrap();
+        assert_eq!(*dest, id);
+        assert!(dest.is_public_key());
+        assert_eq!(Address::from(dest), id);
+
+        let sub = id.with_subresource_id(2).unwrap();
+        let dest = Destination::try_from(sub).unwrap();
+        assert_eq!(dest.address(), &sub);
+        assert_eq!(dest.to_string(), sub.to_string());
+    }
+}
@@ -1,7 +1,11 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::str::FromStr;
+use many_error::ManyError;
 use minicbor::data::Type;
 use minicbor::encode::Write;
 use minicbor::{Decode, Decoder, Encode, Encoder};
-use std::str::FromStr;
 
 impl<C> Encode<C> for crate::Address {
     fn encode<W: Write>(
@@ -15,18 +19,59 @@ impl<C> Encode<C> for crate::Address {
     }
 }
 
+/// The wire form an address was decoded from, see
+/// [crate::Address::decode_with_form].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AddressEncoding {
+    /// The legacy textual form.
+    String,
+
+    /// The bytes form, tagged with the identity tag (10000).
+    TaggedBytes,
+}
+
 impl<'b, C> Decode<'b, C> for crate::Address {
     fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        Self::decode_with_form(d).map(|(address, _)| address)
+    }
+}
+
+impl crate::Address {
+    /// Decodes an address and reports which wire form it used, e.g. to track
+    /// how many peers still send the legacy textual form.
+    pub fn decode_with_form(
+        d: &mut Decoder<'_>,
+    ) -> Result<(Self, AddressEncoding), minicbor::decode::Error> {
+        let start = d.position();
         let mut is_tagged = false;
-        // Check all the tags.
-        while d.datatype()? == Type::Tag {
-            if d.tag()? == minicbor::data::Tag::Unassigned(10000) {
-                is_tagged = true;
+        // Only a single identity tag is allowed.
+        let datatype = loop {
+            match d.datatype() {
+                Ok(Type::Tag) => {
+                    let tag = d.tag()?;
+                    if tag != minicbor::data::Tag::Unassigned(10000) {
+                        return Err(minicbor::decode::Error::message(format!(
+                            "unexpected tag {:?} on identity",
+                            tag
+                        )));
+                    }
+                    if is_tagged {
+                        return Err(minicbor::decode::Error::message(
+                            "identities can only be tagged once",
+                        ));
+                    }
+                    is_tagged = true;
+                }
+                Ok(t) => break t,
+                Err(e) if e.is_end_of_input() && d.position() > start => {
+                    return Err(e.with_message("expected identity value after tag"));
+                }
+                Err(e) => return Err(e),
             }
-        }
+        };
 
-        match d.datatype()? {
-            Type::String => Self::from_str(d.str()?),
+        match datatype {
+            Type::String => Self::from_str(d.str()?).map(|a| (a, AddressEncoding::String)),
             _ => {
                 if !is_tagged {
                     return Err(minicbor::decode::Error::message(
@@ -34,9 +79,511 @@ impl<'b, C> Decode<'b, C> for crate::Address {
                     ));
                 }
 
-                Self::try_from(d.bytes()?)
+                Self::try_from(d.bytes()?).map(|a| (a, AddressEncoding::TaggedBytes))
             }
         }
         .map_err(|_e| minicbor::decode::Error::message("Could not decode identity from bytes"))
     }
+
+    /// Consumes the identity tag (10000) and nothing else, erroring if the
+    /// next item is not that tag. The value is left for the caller to decode,
+    /// e.g. with `Address::try_from(d.bytes()?)`.
+    pub fn expect_tag(d: &mut Decoder<'_>) -> Result<(), minicbor::decode::Error> {
+        match d.datatype()? {
+            Type::Tag => {
+                let position = d.position();
+                let tag = d.tag()?;
+                if tag == minicbor::data::Tag::Unassigned(10000) {
+                    Ok(())
+                } else {
+                    d.set_position(position);
+                    Err(minicbor::decode::Error::message(format!(
+                        "expected identity tag, found {:?}",
+                        tag
+                    )))
+                }
+            }
+            t => {
+                Err(minicbor::decode::Error::type_mismatch(t).with_message("expected identity tag"))
+            }
+        }
+    }
+
+    /// Decodes an optional identity. CBOR `null` or `undefined` is consumed
+    /// and returns `None`. The end of the input or of an indefinite length
+    /// container also returns `None`, without consuming anything, so the
+    /// decoder is left where the surrounding struct expects it.
+    pub fn decode_opt(d: &mut Decoder<'_>) -> Result<Option<Self>, minicbor::decode::Error> {
+        match d.datatype() {
+            Ok(Type::Null) | Ok(Type::Undefined) => {
+                d.skip()?;
+                Ok(None)
+            }
+            Ok(Type::Break) => Ok(None),
+            Err(e) if e.is_end_of_input() => Ok(None),
+            Err(e) => Err(e),
+            Ok(_) => d.decode().map(Some),
+        }
+    }
+
+    /// Returns the identity the next item decodes to, without consuming it.
+    /// Returns `None` if the next item is not a valid identity, e.g. to let a
+    /// decoder branch on the shape of a field.
+    pub fn peek(d: &Decoder<'_>) -> Option<Self> {
+        d.clone().decode().ok()
+    }
+}
+
+/// Decodes a CBOR array of identities, skipping elements that cannot be
+/// decoded instead of failing the whole array. Returns the valid identities
+/// and the errors of the skipped elements. If the array itself cannot be
+/// read (or an element cannot be skipped), decoding stops there.
+pub fn decode_address_array_lenient(
+    bytes: &[u8],
+) -> (Vec<crate::Address>, Vec<minicbor::decode::Error>) {
+    let mut d = Decoder::new(bytes);
+    let mut addresses = Vec::new();
+    let mut errors = Vec::new();
+
+    let len = match d.array() {
+        Ok(len) => len,
+        Err(e) => return (addresses, vec![e]),
+    };
+
+    let mut i = 0;
+    loop {
+        match len {
+            Some(len) if i >= len => break,
+            None if d.datatype().map_or(true, |t| t == Type::Break) => break,
+            _ => {}
+        }
+
+        let start = d.position();
+        match d.decode::<crate::Address>() {
+            Ok(address) => addresses.push(address),
+            Err(e) => {
+                tracing::warn!("Skipping invalid identity at index {}: {}", i, e);
+                errors.push(e);
+                d.set_position(start);
+                if let Err(e) = d.skip() {
+                    errors.push(e);
+                    break;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    (addresses, errors)
+}
+
+/// Decodes a CBOR array of identities one at a time, checking that each
+/// matches the key returned by `keys` (see [crate::Address::matches_key]).
+/// Elements that cannot be decoded or do not match their key are returned as
+/// errors, and the stream continues with the next element. Nothing is
+/// returned after an error reading the array itself.
+#[cfg(feature = "coset")]
+pub fn verify_message_stream<'a, 'b>(
+    d: &'a mut Decoder<'b>,
+    keys: &'a dyn crate::cose::KeyLookup,
+) -> MessageStream<'a, 'b> {
+    MessageStream {
+        d,
+        keys,
+        remaining: None,
+        done: false,
+    }
+}
+
+/// The iterator returned by [verify_message_stream].
+#[cfg(feature = "coset")]
+pub struct MessageStream<'a, 'b> {
+    d: &'a mut Decoder<'b>,
+    keys: &'a dyn crate::cose::KeyLookup,
+
+    /// None until the array header was read, then the remaining length (None
+    /// for indefinite arrays).
+    remaining: Option<Option<u64>>,
+    done: bool,
+}
+
+#[cfg(feature = "coset")]
+impl<'a, 'b> Iterator for MessageStream<'a, 'b> {
+    type Item = Result<crate::Address, ManyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let len = match self.remaining {
+            Some(len) => len,
+            None => match self.d.array() {
+                Ok(len) => *self.remaining.insert(len),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(ManyError::deserialization_error(e)));
+                }
+            },
+        };
+        match len {
+            Some(0) => return None,
+            Some(n) => self.remaining = Some(Some(n - 1)),
+            None => {
+                if self.d.datatype().map_or(true, |t| t == Type::Break) {
+                    return None;
+                }
+            }
+        }
+
+        let start = self.d.position();
+        Some(match self.d.decode::<crate::Address>() {
+            Ok(address) => {
+                if address.matches_key(self.keys.lookup(&address).as_ref()) {
+                    Ok(address)
+                } else {
+                    Err(ManyError::public_key_mismatch(address))
+                }
+            }
+            Err(e) => {
+                self.d.set_position(start);
+                if self.d.skip().is_err() {
+                    self.done = true;
+                }
+                Err(ManyError::deserialization_error(e))
+            }
+        })
+    }
+}
+
+/// Encodes a set of identities as a CBOR array ordered by [crate::Address]'s
+/// `Ord`. The same set always has the same encoding, so it can be signed.
+pub fn encode_address_set_canonical(ids: &BTreeSet<crate::Address>) -> Vec<u8> {
+    // Encoding to a Vec cannot fail.
+    minicbor::to_vec(ids.iter().collect::<Vec<_>>()).unwrap()
+}
+
+/// Decodes a set encoded by [encode_address_set_canonical], rejecting any
+/// encoding that is not canonical (unordered or duplicate elements,
+/// indefinite length or trailing bytes).
+pub fn decode_address_set_canonical(
+    bytes: &[u8],
+) -> Result<BTreeSet<crate::Address>, minicbor::decode::Error> {
+    let mut d = Decoder::new(bytes);
+    let len = d
+        .array()?
+        .ok_or_else(|| minicbor::decode::Error::message("expected a definite length array"))?;
+
+    let mut set = BTreeSet::new();
+    for _ in 0..len {
+        let id: crate::Address = d.decode()?;
+        if set.iter().next_back().map_or(false, |last| last >= &id) {
+            return Err(minicbor::decode::Error::message(
+                "identities must be sorted and unique",
+            ));
+        }
+        set.insert(id);
+    }
+    if d.position() != bytes.len() {
+        return Err(minicbor::decode::Error::message("trailing bytes"));
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+    use crate::Address;
+
+    #[test]
+    fn roundtrip() {
+        let id = identity(1);
+        let bytes = minicbor::to_vec(id).unwrap();
+        assert_eq!(minicbor::decode::<Address>(&bytes).unwrap(), id);
+    }
+
+    #[test]
+    fn tag_without_value() {
+        // Tag 10000, then nothing.
+        let err = minicbor::decode::<Address>(&[0xd9, 0x27, 0x10]).unwrap_err();
+        assert!(err.is_end_of_input());
+        assert!(err
+            .to_string()
+            .contains("expected identity value after tag"));
+    }
+
+    #[test]
+    fn multiple_tags() {
+        let bytes = minicbor::to_vec(identity(1)).unwrap();
+
+        // Tag 10000 twice.
+        let mut twice = vec![0xd9, 0x27, 0x10];
+        twice.extend_from_slice(&bytes);
+        let err = minicbor::decode::<Address>(&twice).unwrap_err();
+        assert!(err.to_string().contains("only be tagged once"));
+
+        // Many nested tags stop at the second one.
+        let nested: Vec<u8> = [0xd9, 0x27, 0x10].repeat(10_000);
+        assert!(minicbor::decode::<Address>(&nested).is_err());
+    }
+
+    #[test]
+    fn wrong_tag() {
+        let bytes = minicbor::to_vec(identity(1)).unwrap();
+
+        // Tag 1 (epoch date) instead of 10000.
+        let mut wrong = vec![0xc1];
+        wrong.extend_from_slice(&bytes[3..]);
+        let err = minicbor::decode::<Address>(&wrong).unwrap_err();
+        assert!(err.to_string().contains("unexpected tag"));
+
+        // Another tag before the identity tag.
+        let mut wrong = vec![0xc1];
+        wrong.extend_from_slice(&bytes);
+        assert!(minicbor::decode::<Address>(&wrong).is_err());
+
+        // A textual identity cannot have another tag either.
+        let mut wrong = vec![0xc1];
+        wrong.extend_from_slice(&minicbor::to_vec(identity(1).to_string()).unwrap());
+        assert!(minicbor::decode::<Address>(&wrong).is_err());
+    }
+
+    #[test]
+    fn decode_with_form() {
+        let id = identity(1);
+
+        let bytes = minicbor::to_vec(id).unwrap();
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(
+            Address::decode_with_form(&mut d).unwrap(),
+            (id, AddressEncoding::TaggedBytes)
+        );
+
+        let bytes = minicbor::to_vec(id.to_string()).unwrap();
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(
+            Address::decode_with_form(&mut d).unwrap(),
+            (id, AddressEncoding::String)
+        );
+    }
+
+    #[test]
+    fn expect_tag() {
+        let id = identity(1);
+        let bytes = minicbor::to_vec(id).unwrap();
+        let mut d = Decoder::new(&bytes);
+        Address::expect_tag(&mut d).unwrap();
+        assert_eq!(d.position(), 3);
+        assert_eq!(Address::try_from(d.bytes().unwrap()).unwrap(), id);
+
+        // Untagged bytes.
+        let bytes = minicbor::to_vec(minicbor::bytes::ByteVec::from(id.to_vec())).unwrap();
+        let mut d = Decoder::new(&bytes);
+        assert!(Address::expect_tag(&mut d).is_err());
+        assert_eq!(d.position(), 0);
+
+        // Another tag.
+        let mut d = Decoder::new(&[0xc1, 0x00]);
+        assert!(Address::expect_tag(&mut d).is_err());
+        assert_eq!(d.position(), 0);
+    }
+
+    #[test]
+    fn peek() {
+        let id = identity(1);
+        let mut bytes = minicbor::to_vec(id).unwrap();
+        bytes.extend_from_slice(&minicbor::to_vec(42u32).unwrap());
+
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(Address::peek(&d), Some(id));
+        assert_eq!(d.position(), 0);
+        assert_eq!(d.decode::<Address>().unwrap(), id);
+
+        let position = d.position();
+        assert_eq!(Address::peek(&d), None);
+        assert_eq!(d.position(), position);
+        assert_eq!(d.u32().unwrap(), 42);
+
+        // The end of the input.
+        assert_eq!(Address::peek(&d), None);
+    }
+
+    #[test]
+    fn array_lenient() {
+        let mut bytes = Vec::new();
+        let mut e = minicbor::Encoder::new(&mut bytes);
+        e.array(4).unwrap();
+        e.encode(identity(1)).unwrap();
+        // Untagged bytes are not a valid identity.
+        e.bytes(&identity(2).to_vec()).unwrap();
+        e.encode(identity(3)).unwrap();
+        e.encode(Address::anonymous()).unwrap();
+
+        let (addresses, errors) = decode_address_array_lenient(&bytes);
+        assert_eq!(
+            addresses,
+            vec![identity(1), identity(3), Address::anonymous()]
+        );
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn array_lenient_indefinite() {
+        let mut bytes = Vec::new();
+        let mut e = minicbor::Encoder::new(&mut bytes);
+        e.begin_array().unwrap();
+        e.u32(1).unwrap();
+        e.encode(identity(1)).unwrap();
+        e.end().unwrap();
+
+        let (addresses, errors) = decode_address_array_lenient(&bytes);
+        assert_eq!(addresses, vec![identity(1)]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn array_lenient_truncated() {
+        let mut bytes = minicbor::to_vec([identity(1), identity(2)]).unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        let (addresses, errors) = decode_address_array_lenient(&bytes);
+        assert_eq!(addresses, vec![identity(1)]);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn set_canonical() {
+        let a = BTreeSet::from([identity(3), identity(1), Address::anonymous()]);
+        let mut b = BTreeSet::new();
+        b.insert(identity(1));
+        b.insert(Address::anonymous());
+        b.insert(identity(3));
+        b.insert(identity(1));
+
+        let bytes = encode_address_set_canonical(&a);
+        assert_eq!(bytes, encode_address_set_canonical(&b));
+        assert_eq!(decode_address_set_canonical(&bytes).unwrap(), a);
+    }
+
+    #[test]
+    fn set_canonical_invalid() {
+        let unordered = minicbor::to_vec([identity(2), identity(1)]).unwrap();
+        assert!(decode_address_set_canonical(&unordered).is_err());
+
+        let duplicate = minicbor::to_vec([identity(1), identity(1)]).unwrap();
+        assert!(decode_address_set_canonical(&duplicate).is_err());
+
+        let mut trailing = encode_address_set_canonical(&BTreeSet::from([identity(1)]));
+        trailing.push(0);
+        assert!(decode_address_set_canonical(&trailing).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct WithOptional {
+        from: Option<Address>,
+        value: u32,
+    }
+
+    impl<'b> minicbor::Decode<'b, ()> for WithOptional {
+        fn decode(d: &mut Decoder<'b>, _: &mut ()) -> Result<Self, minicbor::decode::Error> {
+            d.array()?;
+            let from = Address::decode_opt(d)?;
+            let value = d.u32()?;
+            Ok(Self { from, value })
+        }
+    }
+
+    #[test]
+    fn decode_opt_in_struct() {
+        let mut bytes = Vec::new();
+        let mut e = minicbor::Encoder::new(&mut bytes);
+        e.array(2)
+            .unwrap()
+            .encode(identity(1))
+            .unwrap()
+            .u32(5)
+            .unwrap();
+        assert_eq!(
+            minicbor::decode::<WithOptional>(&bytes).unwrap(),
+            WithOptional {
+                from: Some(identity(1)),
+                value: 5
+            }
+        );
+
+        let mut bytes = Vec::new();
+        let mut e = minicbor::Encoder::new(&mut bytes);
+        e.array(2).unwrap().null().unwrap().u32(5).unwrap();
+        assert_eq!(
+            minicbor::decode::<WithOptional>(&bytes).unwrap(),
+            WithOptional {
+                from: None,
+                value: 5
+            }
+        );
+    }
+
+    #[test]
+    fn decode_opt_absent() {
+        let mut d = Decoder::new(&[]);
+        assert_eq!(Address::decode_opt(&mut d).unwrap(), None);
+
+        // Empty indefinite array; the break is left for the caller.
+        let bytes = [0x9f, 0xff];
+        let mut d = Decoder::new(&bytes);
+        d.array().unwrap();
+        assert_eq!(Address::decode_opt(&mut d).unwrap(), None);
+        assert_eq!(d.datatype().unwrap(), Type::Break);
+
+        // Invalid identities are still errors.
+        let bytes = minicbor::to_vec(5u32).unwrap();
+        assert!(Address::decode_opt(&mut Decoder::new(&bytes)).is_err());
+    }
+
+    #[cfg(feature = "coset")]
+    #[test]
+    fn message_stream() {
+        use many_error::ManyErrorCode;
+        use std::collections::BTreeMap;
+
+        let keys: BTreeMap<Address, coset::CoseKey> = (1..=3u8)
+            .map(|i| {
+                let key = coset::CoseKeyBuilder::new_symmetric_key(vec![i; 16]).build();
+                (crate::cose::address_unchecked(&key).unwrap(), key)
+            })
+            .collect();
+        let lookup = |address: &Address| {
+            keys.iter()
+                .find(|(k, _)| k.matches(address))
+                .map(|(_, v)| v.clone())
+        };
+        let ids: Vec<Address> = keys.keys().copied().collect();
+
+        let mut bytes = Vec::new();
+        let mut e = minicbor::Encoder::new(&mut bytes);
+        e.array(5).unwrap();
+        e.encode(ids[0]).unwrap();
+        // Untagged bytes cannot be decoded.
+        e.bytes(&ids[1].to_vec()).unwrap();
+        // No key for this identity.
+        e.encode(identity(1)).unwrap();
+        e.encode(ids[2].with_subresource_id(1).unwrap()).unwrap();
+        e.encode(Address::anonymous()).unwrap();
+
+        let mut d = Decoder::new(&bytes);
+        let results: Vec<_> = verify_message_stream(&mut d, &lookup).collect();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0], Ok(ids[0]));
+        assert_eq!(
+            results[1].as_ref().unwrap_err().code(),
+            ManyErrorCode::DeserializationError
+        );
+        assert_eq!(
+            results[2].as_ref().unwrap_err().code(),
+            ManyErrorCode::PublicKeyMismatch
+        );
+        assert_eq!(results[3], Ok(ids[2].with_subresource_id(1).unwrap()));
+        assert_eq!(results[4], Ok(Address::anonymous()));
+        assert_eq!(d.position(), bytes.len());
+    }
 }
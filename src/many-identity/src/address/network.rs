@@ -0,0 +1,106 @@
+use crate::Address;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::ops::Deref;
+use core::str::FromStr;
+use many_error::ManyError;
+
+/// The address of a network. It uses the same bytes and textual encoding as
+/// [Address], but with an `n` prefix instead of `m` so it cannot be mistaken
+/// for a user address in logs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[must_use]
+pub struct NetworkId(Address);
+
+impl NetworkId {
+    pub const fn new(address: Address) -> Self {
+        Self(address)
+    }
+
+    pub const fn address(&self) -> &Address {
+        &self.0
+    }
+}
+
+impl From<Address> for NetworkId {
+    fn from(address: Address) -> Self {
+        Self(address)
+    }
+}
+
+impl From<NetworkId> for Address {
+    fn from(network: NetworkId) -> Self {
+        network.0
+    }
+}
+
+impl Deref for NetworkId {
+    type Target = Address;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl core::fmt::Display for NetworkId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let address = self.0.to_string();
+        write!(f, "n{}", address.get(1..).unwrap_or_default())
+    }
+}
+
+impl FromStr for NetworkId {
+    type Err = ManyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('n') {
+            Some(rest) => Address::from_str(&format!("m{}", rest)).map(Self),
+            None => Err(ManyError::invalid_network_id_prefix(
+                s.chars().next().map(String::from).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+
+    #[test]
+    fn roundtrip() {
+        for address in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            let network = NetworkId::from(address);
+            let s = network.to_string();
+            assert!(s.starts_with('n'));
+            assert_eq!(s[1..], address.to_string()[1..]);
+            assert_eq!(NetworkId::from_str(&s), Ok(network));
+            assert_eq!(Address::from(network), address);
+        }
+    }
+
+    #[test]
+    fn address_prefix_is_rejected() {
+        use many_error::ManyErrorCode;
+
+        let s = identity(1).to_string();
+        let err = NetworkId::from_str(&s).unwrap_err();
+        assert_eq!(err.code(), ManyErrorCode::InvalidNetworkIdPrefix);
+        assert_eq!(err.argument("actual"), Some("m"));
+        assert!(err.to_string().contains("Expected 'n', was 'm'"), "{}", err);
+
+        let err = NetworkId::from_str("énetwork").unwrap_err();
+        assert_eq!(err.argument("actual"), Some("é"));
+
+        let err = NetworkId::from_str("").unwrap_err();
+        assert_eq!(err.code(), ManyErrorCode::InvalidNetworkIdPrefix);
+
+        let mut s = NetworkId::from(identity(1)).to_string();
+        s.push('a');
+        assert!(NetworkId::from_str(&s).is_err());
+    }
+}
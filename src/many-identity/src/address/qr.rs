@@ -0,0 +1,75 @@
+//! Segments for encoding an identity into a QR code.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// The QR encoding mode of a segment.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum QrMode {
+    /// The 45 characters `0-9`, `A-Z`, space and `$%*+-./:`.
+    Alphanumeric,
+
+    /// Raw bytes.
+    Byte,
+}
+
+/// A run of text that should be encoded into a QR code using a single mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QrSegment {
+    pub mode: QrMode,
+    pub data: String,
+}
+
+impl QrMode {
+    fn of(c: char) -> Self {
+        match c {
+            '0'..='9' | 'A'..='Z' | ' ' | '$' | '%' | '*' | '+' | '-' | '.' | '/' | ':' => {
+                QrMode::Alphanumeric
+            }
+            _ => QrMode::Byte,
+        }
+    }
+}
+
+/// Split a string into segments of consecutive characters sharing a mode.
+pub(crate) fn segments(s: &str) -> Vec<QrSegment> {
+    let mut result: Vec<QrSegment> = Vec::new();
+    for c in s.chars() {
+        let mode = QrMode::of(c);
+        match result.last_mut() {
+            Some(segment) if segment.mode == mode => segment.data.push(c),
+            _ => result.push(QrSegment {
+                mode,
+                data: c.to_string(),
+            }),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed() {
+        assert_eq!(
+            segments("AB1cd:"),
+            vec![
+                QrSegment {
+                    mode: QrMode::Alphanumeric,
+                    data: "AB1".to_string()
+                },
+                QrSegment {
+                    mode: QrMode::Byte,
+                    data: "cd".to_string()
+                },
+                QrSegment {
+                    mode: QrMode::Alphanumeric,
+                    data: ":".to_string()
+                },
+            ]
+        );
+        assert!(segments("").is_empty());
+    }
+}
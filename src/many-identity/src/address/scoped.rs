@@ -0,0 +1,56 @@
+use crate::Address;
+
+/// An address qualified by the network it belongs to. The same key can exist
+/// on multiple networks, so two scoped addresses are only equal if both their
+/// network and their subject are equal.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScopedAddress {
+    pub network: Address,
+    pub subject: Address,
+}
+
+impl ScopedAddress {
+    pub const fn new(network: Address, subject: Address) -> Self {
+        Self { network, subject }
+    }
+
+    /// Returns true if both scoped addresses refer to the same subject, but
+    /// on different networks.
+    pub fn same_subject_different_network(&self, other: &ScopedAddress) -> bool {
+        self.subject == other.subject && self.network != other.network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+
+    #[test]
+    fn same_subject_different_network() {
+        let a = ScopedAddress::new(identity(1), identity(10));
+        let b = ScopedAddress::new(identity(2), identity(10));
+
+        assert_ne!(a, b);
+        assert!(a.same_subject_different_network(&b));
+        assert!(b.same_subject_different_network(&a));
+    }
+
+    #[test]
+    fn fully_equal() {
+        let a = ScopedAddress::new(identity(1), identity(10));
+        let b = ScopedAddress::new(identity(1), identity(10));
+
+        assert_eq!(a, b);
+        assert!(!a.same_subject_different_network(&b));
+    }
+
+    #[test]
+    fn different_subject() {
+        let a = ScopedAddress::new(identity(1), identity(10));
+        let b = ScopedAddress::new(identity(1), identity(11));
+
+        assert_ne!(a, b);
+        assert!(!a.same_subject_different_network(&b));
+    }
+}
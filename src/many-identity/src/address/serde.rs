@@ -1,8 +1,10 @@
-use crate::address::{Address, InnerAddress};
+use crate::address::Address;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Formatter;
+use core::str::FromStr;
 use serde::de::Error;
 use serde::{Deserialize, Serialize, Serializer};
-use std::fmt::Formatter;
-use std::str::FromStr;
 
 impl Serialize for Address {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -27,7 +29,7 @@ impl<'de> Deserialize<'de> for Address {
         impl<'de> serde::de::Visitor<'de> for Visitor {
             type Value = Address;
 
-            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
                 formatter.write_str("identity string or bytes")
             }
 
@@ -77,61 +79,7 @@ impl<'de> Deserialize<'de> for Address {
         if deserializer.is_human_readable() {
             deserializer.deserialize_str(Visitor)
         } else {
-            deserializer.deserialize_byte_buf(Visitor)
-        }
-    }
-}
-
-struct HumanReadableInnerIdentityVisitor;
-
-impl serde::de::Visitor<'_> for HumanReadableInnerIdentityVisitor {
-    type Value = InnerAddress;
-
-    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
-        formatter.write_str("a textual MANY identity")
-    }
-
-    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        InnerAddress::from_str(v).map_err(E::custom)
-    }
-
-    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        InnerAddress::from_str(v.as_str()).map_err(E::custom)
-    }
-}
-
-struct InnerIdentityVisitor;
-
-impl serde::de::Visitor<'_> for InnerIdentityVisitor {
-    type Value = InnerAddress;
-
-    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
-        formatter.write_str("a byte buffer")
-    }
-
-    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        InnerAddress::from_bytes(v).map_err(E::custom)
-    }
-}
-
-impl<'de> serde::de::Deserialize<'de> for InnerAddress {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::de::Deserializer<'de>,
-    {
-        if deserializer.is_human_readable() {
-            deserializer.deserialize_string(HumanReadableInnerIdentityVisitor)
-        } else {
-            deserializer.deserialize_bytes(InnerIdentityVisitor)
+            deserializer.deserialize_bytes(Visitor)
         }
     }
 }
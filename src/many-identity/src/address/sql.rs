@@ -0,0 +1,60 @@
+use crate::Address;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Binary;
+use many_error::ManyError;
+use std::io::Write;
+
+/// Addresses are stored in `bytea` columns as their canonical bytes (see
+/// [Address::to_vec]).
+impl ToSql<Binary, Pg> for Address {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(&to_sql_bytes(self))?;
+        Ok(serialize::IsNull::No)
+    }
+}
+
+impl FromSql<Binary, Pg> for Address {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        from_sql_bytes(bytes.as_bytes()).map_err(|e| e.to_string().into())
+    }
+}
+
+fn to_sql_bytes(address: &Address) -> Vec<u8> {
+    address.to_vec()
+}
+
+/// Validates the bytes read from a column, so a corrupted row is an error
+/// instead of an invalid address.
+fn from_sql_bytes(bytes: &[u8]) -> Result<Address, ManyError> {
+    Address::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+    use many_error::ManyErrorCode;
+
+    #[test]
+    fn round_trip() {
+        for id in [
+            Address::anonymous(),
+            identity(1),
+            identity(1).with_subresource_id(2).unwrap(),
+        ] {
+            assert_eq!(from_sql_bytes(&to_sql_bytes(&id)), Ok(id));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = to_sql_bytes(&identity(1));
+        bytes[0] = 0x42;
+        assert_eq!(
+            from_sql_bytes(&bytes).unwrap_err().code(),
+            ManyErrorCode::InvalidIdentityKind
+        );
+    }
+}
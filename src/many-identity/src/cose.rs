@@ -1,8 +1,20 @@
 use crate::{Address, Identity};
+use alloc::string::ToString;
 use coset::cbor::value::Value;
 use coset::{AsCborValue, CborSerializable, CoseKey, CoseKeySet, CoseSign1, Label};
 use many_error::ManyError;
-use sha3::{Digest, Sha3_224};
+
+/// Finds the public COSE key of an identity, e.g. from a keyset or a
+/// database.
+pub trait KeyLookup {
+    fn lookup(&self, address: &Address) -> Option<CoseKey>;
+}
+
+impl<F: Fn(&Address) -> Option<CoseKey>> KeyLookup for F {
+    fn lookup(&self, address: &Address) -> Option<CoseKey> {
+        self(address)
+    }
+}
 
 /// Returns the address of a public COSE key.
 ///
@@ -10,14 +22,7 @@ use sha3::{Digest, Sha3_224};
 /// This methods DOES NOT VERIFY that the cose key is of a public key. There are
 /// strict criteria (see spec) for how to define the public key of a COSE Key.
 pub fn address_unchecked(cose_key: &CoseKey) -> Result<Address, ManyError> {
-    let pk = Sha3_224::digest(
-        cose_key
-            .clone()
-            .to_vec()
-            .map_err(|e| ManyError::unknown(e.to_string()))?,
-    );
-
-    Ok(Address::public_key_unchecked(pk.into()))
+    Address::from_cose_key_owned(cose_key.clone())
 }
 
 /// Add the keyset to the protected headers of a CoseSign1 envelope, adding to
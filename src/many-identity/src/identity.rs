@@ -1,5 +1,8 @@
 //! An Identity is a signer that also has an address on the MANY protocol.
 use crate::Address;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
 use coset::{CoseKey, CoseSign1};
 use many_error::ManyError;
 
@@ -120,13 +123,13 @@ macro_rules! decl_verifier_impl {
 decl_identity_impl!(
     impl for Box<dyn Identity>;
     impl<I: Identity> for Box<I>;
-    impl<I: Identity + Sync> for std::sync::Arc<I>;
+    impl<I: Identity + Sync> for alloc::sync::Arc<I>;
 );
 
 decl_verifier_impl!(
     impl for Box<dyn Verifier>;
     impl<I: Verifier> for Box<I>;
-    impl<I: Verifier + Sync> for std::sync::Arc<I>;
+    impl<I: Verifier + Sync> for alloc::sync::Arc<I>;
 );
 
 macro_rules! declare_tuple_verifiers {
@@ -1,5 +1,23 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod address;
-pub use address::Address;
+#[cfg(feature = "corpus")]
+pub use address::address_corpus;
+#[cfg(feature = "std")]
+pub use address::render_addresses;
+pub use address::{
+    address_set_seed, Address, AddressKind, BoundedAddress, Destination, NetworkId, ParseProgress,
+    QrMode, QrSegment, ScopedAddress, TextualDiagnosis,
+};
+#[cfg(feature = "minicbor")]
+pub use address::{
+    decode_address_array_lenient, decode_address_set_canonical, encode_address_set_canonical,
+    AddressEncoding,
+};
+#[cfg(all(feature = "minicbor", feature = "coset"))]
+pub use address::{verify_message_stream, MessageStream};
 
 mod identity;
 pub use identity::*;